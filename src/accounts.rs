@@ -0,0 +1,115 @@
+use std::{ops::Range, process::Command};
+
+use colored::*;
+
+use crate::{
+    signer::{read_seed_phrase, MNEMONIC_ENV, PASSPHRASE_ENV},
+    tui::exit_with_error,
+    Commands,
+};
+
+/// Global arguments replaced for each account's process.
+const ACCOUNT_ARGS: [&str; 2] = ["--account-index", "--keypair"];
+
+/// Runs the command once with each wallet derived for a range of account indexes, each in
+/// its own `ore` process with a single `--account-index`. Every wallet mines at once, while
+/// other commands run one wallet after another.
+pub fn run_each_account(source: &str, range: Range<u32>, command: &Commands) {
+    let (scheme, rest) = source.split_once("://").unwrap_or(("file", source));
+    if !matches!(scheme, "prompt" | "mnemonic") {
+        exit_with_error(
+            "An account index range needs a prompt:// or mnemonic:// keypair".to_string(),
+        );
+    }
+    let mining = match command {
+        Commands::Mine(args) => {
+            if args.tui || args.daemon || args.control_socket.is_some() || args.http_addr.is_some()
+            {
+                exit_with_error(
+                    "--tui, --daemon, --control-socket and --http-addr can't be used with an account index range".to_string(),
+                );
+            }
+            true
+        }
+        _ => false,
+    };
+
+    // Ask for the seed phrase once, and hand it to each process as mnemonic:// reads it
+    let (phrase, passphrase) = read_seed_phrase(scheme);
+    let keypair = format!("mnemonic://{}", rest);
+    let args = strip_account_args(std::env::args().skip(1));
+    let exe = std::env::current_exe().expect("Failed to find the ore executable");
+    let spawn = |index: u32| {
+        Command::new(&exe)
+            .args(&args)
+            .args(["--keypair", &keypair, "--account-index", &index.to_string()])
+            .env(MNEMONIC_ENV, &phrase)
+            .env(PASSPHRASE_ENV, &passphrase)
+            .spawn()
+            .expect("Failed to start ore")
+    };
+
+    let mut failed = false;
+    if mining {
+        // Each miner stops on Ctrl-C by itself, so wait for them rather than exiting
+        tokio::spawn(async { while tokio::signal::ctrl_c().await.is_ok() {} });
+        let children: Vec<_> = range.map(|index| (index, spawn(index))).collect();
+        for (index, mut child) in children {
+            if !child.wait().is_ok_and(|status| status.success()) {
+                println!(
+                    "{} Account index {} exited with an error",
+                    "ERROR".bold().red(),
+                    index
+                );
+                failed = true;
+            }
+        }
+    } else {
+        for index in range {
+            println!("{}", format!("Account index {}", index).bold());
+            failed |= !spawn(index).wait().is_ok_and(|status| status.success());
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// Drops the arguments each account's process is given its own value of.
+fn strip_account_args(mut args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut stripped = vec![];
+    while let Some(arg) = args.next() {
+        if ACCOUNT_ARGS.contains(&arg.as_str()) {
+            args.next();
+        } else if !ACCOUNT_ARGS
+            .iter()
+            .any(|name| arg.starts_with(&format!("{}=", name)))
+        {
+            stripped.push(arg);
+        }
+    }
+    stripped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_account_args_both_forms() {
+        let args = [
+            "--account-index",
+            "0..4",
+            "mine",
+            "--keypair=mnemonic://",
+            "--threads",
+            "2",
+            "--keypair",
+            "prompt://",
+        ];
+        assert_eq!(
+            strip_account_args(args.iter().map(|arg| arg.to_string())),
+            vec!["mine", "--threads", "2"]
+        );
+    }
+}
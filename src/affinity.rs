@@ -0,0 +1,177 @@
+use std::str::FromStr;
+
+use colored::*;
+
+use crate::tui::print_line;
+
+/// Core ids must fit in a `cpu_set_t`, or `CPU_SET` panics.
+#[cfg(target_os = "linux")]
+const MAX_CORES: usize = libc::CPU_SETSIZE as usize;
+#[cfg(not(target_os = "linux"))]
+const MAX_CORES: usize = 1024;
+
+/// Scheduling settings applied to each mining worker thread.
+#[derive(Clone, Debug, Default)]
+pub struct WorkerConfig {
+    /// Cores to pin workers to, assigned round-robin. Empty means no pinning.
+    pub cores: Vec<usize>,
+
+    /// Nice value to lower worker priority by.
+    pub nice: Option<i32>,
+}
+
+impl WorkerConfig {
+    pub fn new(pin_cores: Option<Vec<usize>>, reserve_cores: usize, nice: Option<i32>) -> Self {
+        let num_cores = num_cpus::get();
+        let cores = match pin_cores {
+            Some(cores) => cores,
+            // Keep the first cores free for the async runtime
+            None if reserve_cores > 0 => {
+                if reserve_cores >= num_cores {
+                    print_line(format!(
+                        "{} Cannot reserve {} of {} cores, not pinning workers",
+                        "WARNING".bold().yellow(),
+                        reserve_cores,
+                        num_cores
+                    ));
+                    vec![]
+                } else {
+                    (reserve_cores..num_cores).collect()
+                }
+            }
+            None => vec![],
+        };
+        Self { cores, nice }
+    }
+
+    /// Applies the settings to the calling thread, which is worker `i`.
+    pub fn apply(&self, i: u64) {
+        if !self.cores.is_empty() {
+            let core = self.cores[i as usize % self.cores.len()];
+            if let Err(err) = pin_to_core(core) {
+                print_line(format!(
+                    "{} Failed to pin worker {} to core {}: {}",
+                    "WARNING".bold().yellow(),
+                    i,
+                    core,
+                    err
+                ));
+            }
+        }
+        if let Some(nice) = self.nice {
+            if let Err(err) = set_nice(nice) {
+                print_line(format!(
+                    "{} Failed to set worker {} priority: {}",
+                    "WARNING".bold().yellow(),
+                    i,
+                    err
+                ));
+            }
+        }
+    }
+}
+
+/// A list of core ids, parsed from a string like `0-3,6,8`.
+#[derive(Clone, Debug)]
+pub struct CoreList(pub Vec<usize>);
+
+impl FromStr for CoreList {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_core_list(s).map(CoreList)
+    }
+}
+
+fn parse_core_list(s: &str) -> Result<Vec<usize>, String> {
+    let mut cores = vec![];
+    for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let parse = |n: &str| match n.trim().parse::<usize>() {
+            Ok(core) if core < MAX_CORES => Ok(core),
+            Ok(core) => Err(format!(
+                "core {} is out of range (max {})",
+                core,
+                MAX_CORES - 1
+            )),
+            Err(_) => Err(format!("invalid core {}", n)),
+        };
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(format!("invalid core range {}", part));
+                }
+                cores.extend(start..=end);
+            }
+            None => cores.push(parse(part)?),
+        }
+    }
+    if cores.is_empty() {
+        return Err("no cores given".to_string());
+    }
+    Ok(cores)
+}
+
+#[cfg(target_os = "linux")]
+fn pin_to_core(core: usize) -> std::io::Result<()> {
+    // SAFETY: cpu_set_t is plain data, and 0 targets the calling thread.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn set_nice(nice: i32) -> std::io::Result<()> {
+    // On Linux, nice values apply per thread, addressed by thread id.
+    unsafe {
+        if libc::setpriority(libc::PRIO_PROCESS, libc::gettid() as libc::id_t, nice) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to_core(_core: usize) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "core pinning is only supported on Linux",
+    ))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_nice(_nice: i32) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "per-thread priority is only supported on Linux",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_core_list_values() {
+        assert_eq!(parse_core_list("3").unwrap(), vec![3]);
+        assert_eq!(parse_core_list("0-3").unwrap(), vec![0, 1, 2, 3]);
+        assert_eq!(parse_core_list("0, 2-3 ,8").unwrap(), vec![0, 2, 3, 8]);
+        assert_eq!(parse_core_list("1,").unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn parse_core_list_errors() {
+        assert!(parse_core_list("").is_err());
+        assert!(parse_core_list(",").is_err());
+        assert!(parse_core_list("a").is_err());
+        assert!(parse_core_list("1-b").is_err());
+        assert!(parse_core_list("3-1").is_err());
+        assert!(parse_core_list(&MAX_CORES.to_string()).is_err());
+        assert!(parse_core_list(&format!("0-{}", MAX_CORES)).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use std::str::FromStr;
+
+use colored::*;
+use ore_api::consts::{BUS_ADDRESSES, CONFIG_ADDRESS, MINT_ADDRESS, TREASURY_ADDRESS};
+use solana_program::{
+    address_lookup_table::{
+        instruction::{create_lookup_table, extend_lookup_table},
+        state::AddressLookupTable,
+        AddressLookupTableAccount,
+    },
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signer};
+
+use crate::{
+    args::{AltArgs, AltCommand},
+    send_and_confirm::ComputeBudget,
+    tui::print_line,
+    utils::treasury_tokens_pubkey,
+    Miner,
+};
+
+/// Maximum number of addresses in a single extend instruction that still fits a transaction.
+const MAX_EXTEND_ADDRESSES: usize = 20;
+
+impl Miner {
+    pub async fn alt(&self, args: AltArgs) {
+        let signer = self.signer();
+        match args.command {
+            AltCommand::Create(_) => {
+                // The table address is derived from a recent slot
+                let recent_slot = self
+                    .rpc_client
+                    .get_slot_with_commitment(CommitmentConfig::finalized())
+                    .await
+                    .expect("Failed to fetch slot");
+                let (ix, address) =
+                    create_lookup_table(signer.pubkey(), signer.pubkey(), recent_slot);
+                if self
+                    .send_and_confirm(&[ix], ComputeBudget::Dynamic, false)
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+                println!("Lookup table: {}", address);
+                self.extend_lookup_table(address, ore_lookup_addresses())
+                    .await;
+            }
+            AltCommand::Extend(args) => {
+                let address =
+                    Pubkey::from_str(&args.address).expect("Failed to parse lookup table address");
+                let addresses = if args.addresses.is_empty() {
+                    ore_lookup_addresses()
+                } else {
+                    args.addresses
+                        .iter()
+                        .map(|address| Pubkey::from_str(address).expect("Failed to parse address"))
+                        .collect()
+                };
+                self.extend_lookup_table(address, addresses).await;
+            }
+        }
+    }
+
+    async fn extend_lookup_table(&self, address: Pubkey, addresses: Vec<Pubkey>) {
+        // Skip addresses already in the table
+        let existing = match self.get_lookup_table(&address).await {
+            Some(table) => table.addresses,
+            None => vec![],
+        };
+        let new_addresses: Vec<Pubkey> = addresses
+            .into_iter()
+            .filter(|address| !existing.contains(address))
+            .collect();
+        if new_addresses.is_empty() {
+            println!("Lookup table is up to date");
+            return;
+        }
+        let signer = self.signer();
+        for chunk in new_addresses.chunks(MAX_EXTEND_ADDRESSES) {
+            let ix = extend_lookup_table(
+                address,
+                signer.pubkey(),
+                Some(signer.pubkey()),
+                chunk.to_vec(),
+            );
+            if self
+                .send_and_confirm(&[ix], ComputeBudget::Dynamic, false)
+                .await
+                .is_err()
+            {
+                return;
+            }
+        }
+        println!("Added {} addresses to {}", new_addresses.len(), address);
+    }
+
+    /// The `--lookup-table` account, fetched once and kept for the rest of the session.
+    ///
+    /// Warns and returns `None` if it can't be fetched, so callers can fall back to legacy
+    /// transactions.
+    pub async fn load_lookup_table(&self) -> Option<AddressLookupTableAccount> {
+        let address = self.lookup_table?;
+        if let Some(table) = self.lookup_table_account.get() {
+            return Some(table.clone());
+        }
+        match self.get_lookup_table(&address).await {
+            Some(table) => Some(self.lookup_table_account.get_or_init(|| table).clone()),
+            None => {
+                print_line(format!(
+                    "{} Failed to fetch lookup table {}. Sending legacy transactions.",
+                    "WARNING".bold().yellow(),
+                    address
+                ));
+                None
+            }
+        }
+    }
+
+    pub async fn get_lookup_table(&self, address: &Pubkey) -> Option<AddressLookupTableAccount> {
+        let data = self.rpc_client.get_account_data(address).await.ok()?;
+        let table = AddressLookupTable::deserialize(&data).ok()?;
+        Some(AddressLookupTableAccount {
+            key: *address,
+            addresses: table.addresses.to_vec(),
+        })
+    }
+}
+
+/// Accounts shared by most ORE transactions.
+fn ore_lookup_addresses() -> Vec<Pubkey> {
+    let mut addresses = vec![
+        ore_api::ID,
+        CONFIG_ADDRESS,
+        TREASURY_ADDRESS,
+        treasury_tokens_pubkey(),
+        MINT_ADDRESS,
+        spl_token::id(),
+        spl_associated_token_account::id(),
+        system_program::id(),
+        sysvar::slot_hashes::id(),
+        sysvar::instructions::id(),
+    ];
+    addresses.extend_from_slice(&BUS_ADDRESSES);
+    addresses
+}
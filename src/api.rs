@@ -0,0 +1,393 @@
+use std::{
+    convert::Infallible,
+    net::SocketAddr,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use colored::*;
+use hyper::{
+    body::HttpBody,
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode,
+};
+use ore_api::consts::MINT_ADDRESS;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{native_token::lamports_to_sol, signature::Signer};
+use spl_associated_token_account::get_associated_token_address;
+use subtle::ConstantTimeEq;
+
+use crate::{
+    history::{read_last_records, rounds_path, RoundRecord},
+    utils::{
+        amount_f64_to_u64, amount_u64_to_f64, stake_multiplier, try_get_config,
+        try_get_proof_with_authority,
+    },
+    Miner,
+};
+
+/// Number of rounds `/history` returns when no limit is given.
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// Largest request body read, well above what a claim or stake request needs.
+const MAX_BODY_BYTES: usize = 4 * 1024;
+
+/// The round being mined, as published by the mining loop.
+#[derive(Clone, Serialize, Default, Debug)]
+pub struct RoundStatus {
+    pub challenge: Option<String>,
+
+    /// Unix timestamp of the round's cutoff.
+    pub cutoff_at: Option<i64>,
+    pub min_difficulty: u32,
+    pub best_difficulty: u32,
+
+    /// Hashes per second, across all threads.
+    pub hashrate: f64,
+    pub thread_hashrates: Vec<f64>,
+
+    /// Rounds submitted since the miner started.
+    pub rounds: u64,
+
+    /// Rounds since the miner started which were skipped, as no hash worth the fee was
+    /// found in time.
+    pub skipped_unprofitable: u64,
+
+    /// Rounds since the miner started whose hash was held back, as the fee was over budget.
+    pub skipped_over_budget: u64,
+}
+
+pub type SharedRoundStatus = Arc<Mutex<RoundStatus>>;
+
+struct ApiState {
+    miner: Arc<Miner>,
+    round: SharedRoundStatus,
+    token: Option<String>,
+
+    /// Stake left behind by `POST /claim` without an amount, from `--claim-reserve`.
+    claim_reserve: u64,
+}
+
+#[derive(Deserialize, Default)]
+struct ClaimRequest {
+    amount: Option<f64>,
+    to: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct StakeRequest {
+    amount: Option<f64>,
+}
+
+/// Serves the miner's status over HTTP.
+///
+/// `GET /round`, `/hashrate`, `/history`, `/balances` and `/config` report on the miner.
+/// `POST /claim` and `/stake` send transactions, and need `token` as a bearer token.
+/// They are disabled when no token is set.
+pub fn serve(
+    addr: SocketAddr,
+    miner: Arc<Miner>,
+    round: SharedRoundStatus,
+    token: Option<String>,
+    claim_reserve: u64,
+) -> hyper::Result<()> {
+    let state = Arc::new(ApiState {
+        miner,
+        round,
+        token,
+        claim_reserve,
+    });
+    let server = Server::try_bind(&addr)?.serve(make_service_fn(move |_| {
+        let state = state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let state = state.clone();
+                async move { Ok::<_, Infallible>(handle(&state, request).await) }
+            }))
+        }
+    }));
+    tokio::spawn(async move {
+        if let Err(err) = server.await {
+            eprintln!("{} HTTP server stopped: {}", "ERROR".bold().red(), err);
+        }
+    });
+    Ok(())
+}
+
+async fn handle(state: &ApiState, request: Request<Body>) -> Response<Body> {
+    let path = request.uri().path().trim_end_matches('/').to_string();
+    match (request.method(), path.as_str()) {
+        (&Method::GET, "/round") => {
+            let round = state.round.lock().unwrap().clone();
+            json_response(StatusCode::OK, &round)
+        }
+        (&Method::GET, "/hashrate") => {
+            let round = state.round.lock().unwrap().clone();
+            json_response(
+                StatusCode::OK,
+                &json!({ "hashrate": round.hashrate, "threads": round.thread_hashrates }),
+            )
+        }
+        (&Method::GET, "/history") => history(state, &request),
+        (&Method::GET, "/balances") => balances(state).await,
+        (&Method::GET, "/config") => config(state).await,
+        (&Method::POST, "/claim") => {
+            if let Some(response) = reject_unauthorized(state, &request) {
+                return response;
+            }
+            match read_json::<ClaimRequest>(request).await {
+                Ok(body) => claim(state, body).await,
+                Err(response) => response,
+            }
+        }
+        (&Method::POST, "/stake") => {
+            if let Some(response) = reject_unauthorized(state, &request) {
+                return response;
+            }
+            match read_json::<StakeRequest>(request).await {
+                Ok(body) => stake(state, body).await,
+                Err(response) => response,
+            }
+        }
+        _ => error_response(StatusCode::NOT_FOUND, "Not found"),
+    }
+}
+
+/// Lists the wallet's most recent rounds, oldest first. Takes an optional `limit` query.
+fn history(state: &ApiState, request: &Request<Body>) -> Response<Body> {
+    let limit = match query_param(request, "limit") {
+        Some(limit) => match limit.parse::<usize>() {
+            Ok(limit) => limit,
+            Err(_) => return error_response(StatusCode::BAD_REQUEST, "Invalid limit"),
+        },
+        None => DEFAULT_HISTORY_LIMIT,
+    };
+    let authority = state.miner.signer().pubkey().to_string();
+    let rounds: Vec<RoundRecord> =
+        read_last_records(&rounds_path(), limit, |round: &RoundRecord| {
+            round.authority.eq(&authority)
+        });
+    json_response(StatusCode::OK, &rounds)
+}
+
+async fn balances(state: &ApiState) -> Response<Body> {
+    let miner = &state.miner;
+    let authority = miner.signer().pubkey();
+    let Ok(sol) = miner.rpc_client.get_balance(&authority).await else {
+        return error_response(StatusCode::BAD_GATEWAY, "Failed to fetch SOL balance");
+    };
+    let token_account = get_associated_token_address(&authority, &MINT_ADDRESS);
+    let ore = match miner.rpc_client.get_token_account(&token_account).await {
+        Ok(token_account) => {
+            token_account.and_then(|token_account| token_account.token_amount.ui_amount)
+        }
+        Err(_) => return error_response(StatusCode::BAD_GATEWAY, "Failed to fetch ORE balance"),
+    };
+    let stake = try_get_proof_with_authority(&miner.rpc_client, authority)
+        .await
+        .map_or(0, |proof| proof.balance);
+    let multiplier = try_get_config(&miner.rpc_client)
+        .await
+        .map(|config| stake_multiplier(stake, config.top_balance));
+    json_response(
+        StatusCode::OK,
+        &json!({
+            "authority": authority.to_string(),
+            "sol": lamports_to_sol(sol),
+            "ore": ore.unwrap_or(0.0),
+            "stake": amount_u64_to_f64(stake),
+            "multiplier": multiplier,
+        }),
+    )
+}
+
+async fn config(state: &ApiState) -> Response<Body> {
+    let Some(config) = try_get_config(&state.miner.rpc_client).await else {
+        return error_response(StatusCode::BAD_GATEWAY, "Failed to fetch config");
+    };
+    json_response(
+        StatusCode::OK,
+        &json!({
+            "base_reward_rate": config.base_reward_rate,
+            "last_reset_at": config.last_reset_at,
+            "min_difficulty": config.min_difficulty,
+            "top_balance": config.top_balance,
+        }),
+    )
+}
+
+/// Claims the given amount, or the stake balance above `--claim-reserve`, to the given wallet
+/// or the miner's.
+async fn claim(state: &ApiState, body: ClaimRequest) -> Response<Body> {
+    let miner = &state.miner;
+    let wallet = match body.to.as_deref().map(Pubkey::from_str) {
+        Some(Ok(wallet)) => wallet,
+        Some(Err(_)) => return error_response(StatusCode::BAD_REQUEST, "Invalid wallet address"),
+        None => miner.signer().pubkey(),
+    };
+    let Some(proof) =
+        try_get_proof_with_authority(&miner.rpc_client, miner.signer().pubkey()).await
+    else {
+        return error_response(StatusCode::BAD_GATEWAY, "Failed to fetch proof");
+    };
+    let amount = match body.amount {
+        Some(amount) => amount_f64_to_u64(amount),
+        None => proof.balance.saturating_sub(state.claim_reserve),
+    };
+    if let Err(message) = check_amount(amount, proof.balance, "claim", "stake") {
+        return error_response(StatusCode::BAD_REQUEST, &message);
+    }
+    match miner.send_claim(wallet, amount, "api").await {
+        Ok(signature) => json_response(
+            StatusCode::OK,
+            &json!({ "signature": signature.to_string(), "amount": amount_u64_to_f64(amount) }),
+        ),
+        Err(err) => error_response(StatusCode::BAD_GATEWAY, &err.to_string()),
+    }
+}
+
+/// Stakes the given amount, or the wallet's whole ORE balance.
+async fn stake(state: &ApiState, body: StakeRequest) -> Response<Body> {
+    let miner = &state.miner;
+    let token_account = get_associated_token_address(&miner.signer().pubkey(), &MINT_ADDRESS);
+    let balance = match miner.rpc_client.get_token_account(&token_account).await {
+        Ok(Some(token_account)) => token_account.token_amount.amount.parse().unwrap_or(0),
+        _ => return error_response(StatusCode::BAD_GATEWAY, "Failed to fetch ORE balance"),
+    };
+    let amount = body.amount.map_or(balance, amount_f64_to_u64);
+    if let Err(message) = check_amount(amount, balance, "stake", "wallet") {
+        return error_response(StatusCode::BAD_REQUEST, &message);
+    }
+    match miner.send_stake(amount).await {
+        Ok(signature) => json_response(
+            StatusCode::OK,
+            &json!({ "signature": signature.to_string(), "amount": amount_u64_to_f64(amount) }),
+        ),
+        Err(err) => error_response(StatusCode::BAD_GATEWAY, &err.to_string()),
+    }
+}
+
+/// Checks an amount to claim or stake is more than zero and no more than the balance it
+/// comes out of.
+fn check_amount(amount: u64, balance: u64, action: &str, source: &str) -> Result<(), String> {
+    if amount == 0 {
+        return Err(format!("Nothing to {}", action));
+    }
+    if amount.gt(&balance) {
+        return Err(format!(
+            "Amount is more than the {} balance of {} ORE",
+            source,
+            amount_u64_to_f64(balance)
+        ));
+    }
+    Ok(())
+}
+
+/// Checks the request carries the configured bearer token, returning the error response if
+/// it doesn't.
+fn reject_unauthorized(state: &ApiState, request: &Request<Body>) -> Option<Response<Body>> {
+    let Some(token) = state.token.as_deref() else {
+        return Some(error_response(
+            StatusCode::FORBIDDEN,
+            "POST endpoints are disabled without --http-token",
+        ));
+    };
+    let bearer = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "));
+
+    // Compare in constant time, so response times don't leak the token
+    let valid = bearer.is_some_and(|bearer| bearer.as_bytes().ct_eq(token.as_bytes()).into());
+    (!valid).then(|| error_response(StatusCode::UNAUTHORIZED, "Invalid token"))
+}
+
+/// Parses the request body as JSON, treating an empty body as the default request.
+///
+/// Stops reading once the body grows past `MAX_BODY_BYTES`.
+async fn read_json<T: for<'de> Deserialize<'de> + Default>(
+    request: Request<Body>,
+) -> Result<T, Response<Body>> {
+    let mut body = request.into_body();
+    let mut bytes = vec![];
+    while let Some(chunk) = body.data().await {
+        let Ok(chunk) = chunk else {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                "Failed to read body",
+            ));
+        };
+        if bytes.len() + chunk.len() > MAX_BODY_BYTES {
+            return Err(error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Body is too large",
+            ));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    if bytes.iter().all(u8::is_ascii_whitespace) {
+        return Ok(T::default());
+    }
+    serde_json::from_slice(&bytes)
+        .map_err(|err| error_response(StatusCode::BAD_REQUEST, &format!("Invalid body: {}", err)))
+}
+
+fn query_param(request: &Request<Body>, name: &str) -> Option<String> {
+    request.uri().query()?.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        key.eq(name).then(|| value.to_string())
+    })
+}
+
+fn json_response<T: Serialize + ?Sized>(status: StatusCode, value: &T) -> Response<Body> {
+    let body = serde_json::to_string(value).unwrap_or_default();
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, &json!({ "error": message }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read_stake(body: &[u8]) -> Result<StakeRequest, StatusCode> {
+        read_json(Request::new(Body::from(body.to_vec())))
+            .await
+            .map_err(|response| response.status())
+    }
+
+    #[tokio::test]
+    async fn read_json_caps_the_body() {
+        assert!(read_stake(b"")
+            .await
+            .is_ok_and(|body| body.amount.is_none()));
+        assert!(read_stake(br#"{"amount": 1.5}"#)
+            .await
+            .is_ok_and(|body| body.amount == Some(1.5)));
+        assert_eq!(read_stake(b"{").await.err(), Some(StatusCode::BAD_REQUEST));
+        assert_eq!(
+            read_stake(&[b' '; MAX_BODY_BYTES + 1]).await.err(),
+            Some(StatusCode::PAYLOAD_TOO_LARGE)
+        );
+    }
+
+    #[test]
+    fn check_amount_bounds() {
+        assert!(check_amount(1, 1, "claim", "stake").is_ok());
+        assert_eq!(
+            check_amount(0, 10, "claim", "stake"),
+            Err("Nothing to claim".to_string())
+        );
+        assert!(check_amount(11, 10, "stake", "wallet").is_err());
+    }
+}
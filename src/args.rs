@@ -1,4 +1,43 @@
-use clap::{arg, Parser};
+use std::{net::SocketAddr, ops::Range, str::FromStr};
+
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+
+use crate::{affinity::CoreList, schedule::Schedule};
+
+#[derive(Parser, Debug)]
+pub struct AltArgs {
+    #[command(subcommand)]
+    pub command: AltCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AltCommand {
+    #[command(about = "Create a lookup table pre-populated with the ORE accounts")]
+    Create(AltCreateArgs),
+
+    #[command(about = "Add addresses to a lookup table")]
+    Extend(AltExtendArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct AltCreateArgs {}
+
+#[derive(Parser, Debug)]
+pub struct AltExtendArgs {
+    #[arg(
+        value_name = "LOOKUP_TABLE_ADDRESS",
+        help = "The lookup table to extend"
+    )]
+    pub address: String,
+
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "Addresses to add. Defaults to the ORE accounts.",
+        value_delimiter = ','
+    )]
+    pub addresses: Vec<String>,
+}
 
 #[derive(Parser, Debug)]
 pub struct BalanceArgs {
@@ -20,6 +59,55 @@ pub struct BenchmarkArgs {
         default_value = "1"
     )]
     pub threads: u64,
+
+    #[arg(
+        long,
+        short,
+        value_name = "SECONDS",
+        help = "The number of seconds to run the benchmark for",
+        default_value = "30"
+    )]
+    pub duration: u64,
+
+    #[arg(
+        long,
+        help = "Also benchmark without reusing solver memory, for comparison",
+        default_value = "false"
+    )]
+    pub compare_memory: bool,
+
+    #[arg(long, help = "Print the results as JSON", default_value = "false")]
+    pub json: bool,
+
+    #[arg(
+        long,
+        help = "Benchmark every thread count up to --sweep-max and save the best to the profile",
+        default_value = "false"
+    )]
+    pub sweep: bool,
+
+    #[arg(
+        long,
+        value_name = "THREAD_COUNT",
+        help = "The largest thread count to try with --sweep. Defaults to the number of cores."
+    )]
+    pub sweep_max: Option<u64>,
+}
+
+#[derive(Parser, Debug)]
+pub struct BroadcastArgs {
+    #[arg(
+        value_name = "TRANSACTION",
+        help = "A signed transaction, as printed by --sign-only (base58 or base64)"
+    )]
+    pub transaction: String,
+
+    #[arg(
+        long = "signer",
+        value_name = "PUBKEY=SIGNATURE",
+        help = "Signature of a signer the transaction is missing. Can be repeated."
+    )]
+    pub signers: Vec<String>,
 }
 
 #[derive(Parser, Debug)]
@@ -40,17 +128,216 @@ pub struct ClaimArgs {
         help = "Wallet to receive claimed tokens."
     )]
     pub to: Option<String>,
-	
-	
-	
+
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Amount of rewards to leave staked when claiming the max",
+        default_value = "0",
+        conflicts_with = "amount"
+    )]
+    pub reserve: f64,
+
+    #[arg(
+        long,
+        value_name = "DIRECTORY",
+        help = "Claim from every keypair in this directory, paying fees from the main keypair",
+        conflicts_with_all = ["amount", "sign_only"]
+    )]
+    pub keypairs: Option<String>,
+
+    #[command(flatten)]
+    pub sign: SignArgs,
 }
 
 #[derive(Parser, Debug)]
-pub struct CloseArgs {}
+pub struct CloseArgs {
+    #[command(flatten)]
+    pub sign: SignArgs,
+}
 
 #[derive(Parser, Debug)]
 pub struct ConfigArgs {}
 
+#[derive(Parser, Debug)]
+pub struct CtlArgs {
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Control socket of the miner. Defaults to the one used by `ore mine --daemon`."
+    )]
+    pub control_socket: Option<String>,
+
+    #[command(subcommand)]
+    pub command: CtlCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CtlCommand {
+    #[command(about = "Show the miner's current settings")]
+    Status,
+
+    #[command(about = "Stop mining after the current round")]
+    Pause,
+
+    #[command(about = "Resume mining")]
+    Resume,
+
+    #[command(about = "Change the number of mining threads")]
+    Threads {
+        #[arg(value_name = "THREAD_COUNT")]
+        threads: u64,
+    },
+
+    #[command(about = "Change the minimum difficulty to submit")]
+    Difficulty {
+        #[arg(value_name = "DIFFICULTY")]
+        difficulty: u32,
+    },
+
+    #[command(about = "Change the priority fee of mine transactions")]
+    PriorityFee {
+        #[arg(value_name = "MICROLAMPORTS")]
+        priority_fee: u64,
+    },
+
+    #[command(about = "Claim rewards after the current round")]
+    Claim,
+}
+
+#[derive(Parser, Debug)]
+pub struct EstimateArgs {
+    #[arg(
+        long,
+        value_name = "HASHES_PER_SEC",
+        help = "Your hashrate. Measured with a short benchmark if not given."
+    )]
+    pub hashrate: Option<f64>,
+
+    #[arg(
+        long,
+        short,
+        value_name = "THREAD_COUNT",
+        help = "The number of threads to benchmark with. Defaults to the tuned profile or the number of cores.",
+        conflicts_with = "hashrate"
+    )]
+    pub threads: Option<u64>,
+
+    #[arg(
+        long,
+        short,
+        value_name = "SECONDS",
+        help = "The number of seconds to benchmark for",
+        default_value = "10",
+        conflicts_with = "hashrate"
+    )]
+    pub duration: u64,
+
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Stake balance to estimate with. Defaults to your current stake."
+    )]
+    pub stake: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "SOL",
+        help = "Price of 1 ORE in SOL, to compute the break-even priority fee"
+    )]
+    pub ore_price: Option<f64>,
+}
+
+#[derive(Parser, Debug)]
+pub struct ExportArgs {
+    #[arg(
+        long,
+        value_name = "DATE",
+        help = "Only include transactions on or after this day (YYYY-MM-DD, local time)"
+    )]
+    pub from: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DATE",
+        help = "Only include transactions on or before this day (YYYY-MM-DD, local time)"
+    )]
+    pub to: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        help = "Output format",
+        default_value = "csv"
+    )]
+    pub format: ExportFormat,
+
+    #[arg(
+        long,
+        value_name = "WALLET_ADDRESS",
+        help = "Wallet to export. Defaults to your keypair."
+    )]
+    pub wallet: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILEPATH",
+        help = "File to write to. Defaults to stdout."
+    )]
+    pub output: Option<String>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Parser, Debug)]
+pub struct HistoryArgs {
+    #[arg(
+        long,
+        value_name = "DATE",
+        help = "Only include rounds on or after this day (YYYY-MM-DD, local time)"
+    )]
+    pub from: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DATE",
+        help = "Only include rounds on or before this day (YYYY-MM-DD, local time)"
+    )]
+    pub to: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "WALLET_ADDRESS",
+        help = "Only include rounds mined by this wallet"
+    )]
+    pub wallet: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "GROUP",
+        help = "How to summarize rounds",
+        default_value = "day"
+    )]
+    pub group_by: HistoryGroup,
+
+    #[arg(
+        long,
+        help = "List individual rounds instead of a summary",
+        default_value = "false"
+    )]
+    pub rounds: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum HistoryGroup {
+    Day,
+    Wallet,
+}
+
 #[cfg(feature = "admin")]
 #[derive(Parser, Debug)]
 pub struct PauseArgs {}
@@ -59,6 +346,85 @@ pub struct PauseArgs {}
 #[derive(Parser, Debug)]
 pub struct InitializeArgs {}
 
+#[derive(Parser, Debug)]
+pub struct KeysArgs {
+    #[command(subcommand)]
+    pub command: KeysCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum KeysCommand {
+    #[command(about = "Generate a new keypair into an encrypted keystore")]
+    New(KeysNewArgs),
+
+    #[command(about = "Encrypt an existing keypair file into a keystore")]
+    Import(KeysImportArgs),
+
+    #[command(about = "Decrypt a keystore into a plaintext keypair file")]
+    Export(KeysExportArgs),
+
+    #[command(about = "List the keystores in the keystore directory")]
+    List(KeysListArgs),
+
+    #[command(
+        about = "Print the addresses derived from a seed phrase, for use with --account-index"
+    )]
+    Derive(KeysDeriveArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct KeysNewArgs {
+    #[arg(
+        long,
+        value_name = "FILEPATH",
+        help = "Where to write the keystore. Defaults to the keystore directory."
+    )]
+    pub outfile: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct KeysImportArgs {
+    #[arg(
+        value_name = "KEYPAIR_FILEPATH",
+        help = "Plaintext keypair file to encrypt"
+    )]
+    pub keypair: String,
+
+    #[arg(
+        long,
+        value_name = "FILEPATH",
+        help = "Where to write the keystore. Defaults to the keystore directory."
+    )]
+    pub outfile: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct KeysExportArgs {
+    #[arg(value_name = "KEYSTORE_FILEPATH", help = "Keystore to decrypt")]
+    pub keystore: String,
+
+    #[arg(
+        long,
+        value_name = "FILEPATH",
+        help = "Where to write the plaintext keypair file."
+    )]
+    pub outfile: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct KeysListArgs {}
+
+#[derive(Parser, Debug)]
+pub struct KeysDeriveArgs {
+    #[arg(
+        long,
+        value_name = "START..END",
+        help = "The range of account indexes to print the addresses of",
+        default_value = "0..10"
+    )]
+    pub range: String,
+}
+
 #[derive(Parser, Debug)]
 pub struct MineArgs {
     // #[cfg(not(feature = "gpu"))]
@@ -66,10 +432,10 @@ pub struct MineArgs {
         long,
         short,
         value_name = "THREAD_COUNT",
-        help = "The number of CPU threads to allocate to mining",
+        help = "The number of CPU threads to allocate to mining, or \"auto\" to use the count found by `ore benchmark --sweep`",
         default_value = "1"
     )]
-    pub threads: u64,
+    pub threads: Threads,
 
     #[arg(
         long,
@@ -79,9 +445,8 @@ pub struct MineArgs {
         default_value = "5"
     )]
     pub buffer_time: u64,
-	
-	
-	 #[arg(
+
+    #[arg(
         long,
         short,
         value_name = "THREAD_COUNT",
@@ -89,7 +454,411 @@ pub struct MineArgs {
         default_value = "10"
     )]
     pub nandu: u64,
-	
+
+    #[arg(
+        long,
+        value_name = "CORES",
+        help = "Pin each mining thread to one of these cores, e.g. 0-3,6"
+    )]
+    pub pin_cores: Option<CoreList>,
+
+    #[arg(
+        long,
+        value_name = "CORE_COUNT",
+        help = "Pin mining threads to all but the first CORE_COUNT cores, leaving those free for the RPC runtime and the rest of the system. The runtime itself is not pinned.",
+        default_value = "0",
+        conflicts_with = "pin_cores"
+    )]
+    pub reserve_cores: usize,
+
+    #[arg(
+        long,
+        value_name = "NICE",
+        help = "Lower the priority of mining threads to this nice value (0-19)",
+        value_parser = clap::value_parser!(i32).range(0..=19)
+    )]
+    pub nice: Option<i32>,
+
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Stake your wallet's Ore between rounds whenever it holds more than this amount"
+    )]
+    pub auto_stake: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Minimum seconds between auto-stakes. Each stake forfeits the multiplier for the next round.",
+        default_value = "3600",
+        requires = "auto_stake"
+    )]
+    pub auto_stake_interval: u64,
+
+    #[arg(
+        long,
+        help = "Log what auto-stake would do without sending anything",
+        default_value = "false",
+        requires = "auto_stake"
+    )]
+    pub auto_stake_dry_run: bool,
+
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Claim rewards between rounds whenever the stake balance exceeds this amount"
+    )]
+    pub auto_claim_above: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "SCHEDULE",
+        help = "Claim rewards between rounds on a cron-like schedule in local time, e.g. \"0 */6 * * *\""
+    )]
+    pub auto_claim_schedule: Option<Schedule>,
+
+    #[arg(
+        long,
+        value_name = "WALLET_ADDRESS",
+        help = "Wallet to receive auto-claimed tokens. Defaults to the miner's wallet, unless auto-staking."
+    )]
+    pub claim_to: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Amount of rewards to leave staked when auto-claiming",
+        default_value = "0"
+    )]
+    pub claim_reserve: f64,
+
+    #[arg(
+        long,
+        help = "Show a live dashboard of the miner instead of a progress bar",
+        default_value = "false"
+    )]
+    pub tui: bool,
+
+    #[arg(
+        long,
+        help = "Run as a long-lived service controlled through the control socket",
+        default_value = "false",
+        conflicts_with = "tui"
+    )]
+    pub daemon: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Listen for `ore ctl` commands on this Unix socket. Defaults to ~/.config/ore-cli/mine.sock with --daemon."
+    )]
+    pub control_socket: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "Serve the miner's status over HTTP on this address, e.g. 127.0.0.1:8080"
+    )]
+    pub http_addr: Option<SocketAddr>,
+
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        help = "Bearer token required by the HTTP claim and stake endpoints. Falls back to ORE_HTTP_TOKEN.",
+        requires = "http_addr"
+    )]
+    pub http_token: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "POST mining alerts as JSON to this URL. Can be repeated."
+    )]
+    pub webhook: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "Post mining alerts to this Slack incoming webhook. Can be repeated."
+    )]
+    pub slack_webhook: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "URL",
+        help = "Post mining alerts to this Discord webhook. Can be repeated."
+    )]
+    pub discord_webhook: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        help = "Run this shell command on mining alerts, with the alert in ORE_EVENT, ORE_MESSAGE and ORE_EVENT_JSON"
+    )]
+    pub notify_command: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SOL",
+        help = "Alert when the payer balance falls below this amount. Defaults to 10 times --min-sol-balance."
+    )]
+    pub alert_balance: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "COUNT",
+        help = "Alert when this many mine transactions fail in a row",
+        default_value = "3"
+    )]
+    pub alert_failures: u64,
+
+    #[arg(
+        long,
+        value_name = "LEVELS",
+        help = "Alert when the program's minimum difficulty rises by at least this many levels between rounds"
+    )]
+    pub alert_difficulty_spike: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Pause until the payer is funded instead of exiting when its balance is too low",
+        default_value = "false"
+    )]
+    pub wait_for_funds: bool,
+
+    #[arg(
+        long,
+        value_name = "KEYPAIR_SOURCE",
+        help = "Keypair to top up the payer from when its balance is too low"
+    )]
+    pub top_up_keypair: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SOL",
+        help = "Amount of SOL to send in each top-up",
+        default_value = "0.1",
+        requires = "top_up_keypair"
+    )]
+    pub top_up_amount: f64,
+
+    #[arg(
+        long,
+        value_name = "SOL",
+        help = "Most SOL to top up per day, in local time",
+        default_value = "1",
+        requires = "top_up_keypair"
+    )]
+    pub top_up_daily_cap: f64,
+
+    #[arg(
+        long,
+        value_name = "SOL",
+        help = "Stop mining once this session's transaction fees would go over this amount"
+    )]
+    pub session_budget: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "SOL",
+        help = "Most SOL to spend on transaction fees per day, in local time. Mining waits for the next day once it is spent."
+    )]
+    pub daily_budget: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "SOL",
+        help = "Price of 1 ORE in SOL. Hashes whose reward is worth less than the transaction fee are held back while mining for a better one."
+    )]
+    pub ore_price: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "MINUTES",
+        help = "Minutes past the cutoff to keep mining for a hash worth its fee, before skipping the round. Mining carries on with the same challenge.",
+        default_value = "1",
+        requires = "ore_price"
+    )]
+    pub unprofitable_holdout: u64,
+
+    #[arg(
+        long,
+        help = "Submit the best hash once --unprofitable-holdout runs out, even if its reward is worth less than the fee, rather than skipping the round.",
+        default_value = "false",
+        requires = "ore_price"
+    )]
+    pub submit_unprofitable: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct NonceArgs {
+    #[command(subcommand)]
+    pub command: NonceCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum NonceCommand {
+    #[command(about = "Create a durable nonce account")]
+    Create(NonceCreateArgs),
+
+    #[command(about = "Fetch a durable nonce account")]
+    Show(NonceShowArgs),
+
+    #[command(about = "Advance a durable nonce, invalidating transactions signed with it")]
+    Advance(NonceAdvanceArgs),
+
+    #[command(about = "Withdraw SOL from a durable nonce account")]
+    Withdraw(NonceWithdrawArgs),
+}
+
+#[derive(Parser, Debug)]
+pub struct NonceCreateArgs {
+    #[arg(
+        long,
+        value_name = "SEED",
+        help = "Seed used to derive the nonce account address from the signer",
+        default_value = "nonce"
+    )]
+    pub seed: String,
+
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        help = "Authority allowed to advance the nonce. Defaults to the signer."
+    )]
+    pub authority: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SOL",
+        help = "The amount of SOL to fund the account with. Defaults to the rent-exempt minimum."
+    )]
+    pub amount: Option<f64>,
+}
+
+#[derive(Parser, Debug)]
+pub struct NonceShowArgs {
+    #[arg(
+        value_name = "ADDRESS",
+        help = "The nonce account. Defaults to the account derived from --seed."
+    )]
+    pub address: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SEED",
+        help = "Seed used to derive the nonce account address from the signer",
+        default_value = "nonce"
+    )]
+    pub seed: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct NonceAdvanceArgs {
+    #[arg(
+        value_name = "ADDRESS",
+        help = "The nonce account. Defaults to the account derived from --seed."
+    )]
+    pub address: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SEED",
+        help = "Seed used to derive the nonce account address from the signer",
+        default_value = "nonce"
+    )]
+    pub seed: String,
+
+    #[arg(
+        long,
+        value_name = "KEYPAIR_SOURCE",
+        help = "Keypair authorized to advance the nonce. Defaults to the signer."
+    )]
+    pub nonce_authority: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct NonceWithdrawArgs {
+    #[arg(
+        value_name = "ADDRESS",
+        help = "The nonce account. Defaults to the account derived from --seed."
+    )]
+    pub address: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SEED",
+        help = "Seed used to derive the nonce account address from the signer",
+        default_value = "nonce"
+    )]
+    pub seed: String,
+
+    #[arg(
+        long,
+        value_name = "SOL",
+        help = "The amount of SOL to withdraw. Defaults to max, closing the account."
+    )]
+    pub amount: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "WALLET_ADDRESS",
+        help = "Wallet to receive the SOL. Defaults to the signer."
+    )]
+    pub to: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "KEYPAIR_SOURCE",
+        help = "Keypair authorized to withdraw from the nonce account. Defaults to the signer."
+    )]
+    pub nonce_authority: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Threads {
+    Auto,
+    Count(u64),
+}
+
+impl FromStr for Threads {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Threads::Auto),
+            _ => match s.parse() {
+                Ok(0) => Err("expected at least 1 thread".to_string()),
+                Ok(threads) => Ok(Threads::Count(threads)),
+                Err(_) => Err(format!("expected a number or \"auto\", got {}", s)),
+            },
+        }
+    }
+}
+
+/// A single account index, or a range of them to run a command with each derived wallet.
+#[derive(Clone, Debug)]
+pub enum AccountIndex {
+    One(u32),
+    Range(Range<u32>),
+}
+
+impl FromStr for AccountIndex {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((start, end)) = s.split_once("..") else {
+            return s
+                .parse()
+                .map(AccountIndex::One)
+                .map_err(|_| format!("expected an index or START..END, got {}", s));
+        };
+        match (start.parse(), end.parse()) {
+            (Ok(start), Ok(end)) if start < end => Ok(AccountIndex::Range(start..end)),
+            (Ok(_), Ok(_)) => Err(format!("expected START below END, got {}", s)),
+            _ => Err(format!("expected an index or START..END, got {}", s)),
+        }
+    }
 }
 
 #[derive(Parser, Debug)]
@@ -110,6 +879,25 @@ pub struct StakeArgs {
         help = "Token account to send Ore from."
     )]
     pub sender: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Show how staking this amount of Ore would change your multiplier, without sending anything",
+        conflicts_with_all = ["amount", "sign_only"]
+    )]
+    pub simulate: Option<f64>,
+
+    #[arg(
+        long,
+        value_name = "HASHES_PER_SEC",
+        help = "Your hashrate, to project expected rewards with --simulate",
+        requires = "simulate"
+    )]
+    pub hashrate: Option<f64>,
+
+    #[command(flatten)]
+    pub sign: SignArgs,
 }
 
 #[cfg(feature = "admin")]
@@ -126,4 +914,90 @@ pub struct UpgradeArgs {
         help = "The amount of Ore to upgrade from v1 to v2. Defaults to max."
     )]
     pub amount: Option<f64>,
+
+    #[command(flatten)]
+    pub sign: SignArgs,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[command(group(ArgGroup::new("hash_source").args(["blockhash", "nonce"]).multiple(true)))]
+pub struct SignArgs {
+    #[arg(
+        long,
+        help = "Sign the transaction and print it instead of submitting it. Requires --blockhash or --nonce.",
+        default_value = "false",
+        requires = "hash_source"
+    )]
+    pub sign_only: bool,
+
+    #[arg(
+        long,
+        value_name = "BLOCKHASH",
+        help = "Blockhash to sign with instead of fetching the latest one"
+    )]
+    pub blockhash: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "NONCE_ADDRESS",
+        help = "Durable nonce account to use in place of a recent blockhash"
+    )]
+    pub nonce: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "KEYPAIR_SOURCE",
+        help = "Keypair authorized to advance the nonce. Defaults to the signer. With --sign-only, it can be an address, to sign elsewhere.",
+        requires = "nonce"
+    )]
+    pub nonce_authority: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "ENCODING",
+        help = "Encoding of the transaction printed by --sign-only",
+        default_value = "base64"
+    )]
+    pub encoding: TxEncoding,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum TxEncoding {
+    Base58,
+    Base64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threads_from_str() {
+        assert!(matches!(Threads::from_str("auto"), Ok(Threads::Auto)));
+        assert!(matches!(Threads::from_str("1"), Ok(Threads::Count(1))));
+        assert!(matches!(Threads::from_str("16"), Ok(Threads::Count(16))));
+        assert!(Threads::from_str("0").is_err());
+        assert!(Threads::from_str("-1").is_err());
+        assert!(Threads::from_str("Auto").is_err());
+        assert!(Threads::from_str("").is_err());
+    }
+
+    #[test]
+    fn account_index_from_str() {
+        assert!(matches!(
+            AccountIndex::from_str("0"),
+            Ok(AccountIndex::One(0))
+        ));
+        assert!(matches!(
+            AccountIndex::from_str("7"),
+            Ok(AccountIndex::One(7))
+        ));
+        assert!(
+            matches!(AccountIndex::from_str("0..4"), Ok(AccountIndex::Range(range)) if range == (0..4))
+        );
+        assert!(AccountIndex::from_str("4..4").is_err());
+        assert!(AccountIndex::from_str("4..2").is_err());
+        assert!(AccountIndex::from_str("0..").is_err());
+        assert!(AccountIndex::from_str("-1").is_err());
+    }
 }
@@ -0,0 +1,105 @@
+use std::time::Duration;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use ore_api::{event::MineEvent, state::Proof};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+    UiTransactionEncoding,
+};
+
+use crate::Miner;
+
+/// Number of times to look for a confirmed transaction before giving up.
+const FETCH_RETRIES: usize = 5;
+
+/// Delay between attempts to fetch a confirmed transaction.
+const FETCH_DELAY: Duration = Duration::from_secs(1);
+
+/// The reward credited for a landed hash.
+#[derive(Clone, Copy, Debug)]
+pub struct MineReward {
+    pub difficulty: u64,
+    pub reward: u64,
+
+    /// Seconds between the hash landing and its deadline, as reported by the program.
+    pub timing: Option<i64>,
+
+    /// Whether the reward is the change in the proof balance, as the transaction could not
+    /// be read. A claim or stake landing in between throws it off.
+    pub estimated: bool,
+}
+
+impl Miner {
+    /// Works out what a landed mine transaction earned.
+    ///
+    /// The program returns a `MineEvent` with the difficulty and reward it credited, so that
+    /// is read from the transaction when possible. Otherwise the reward is estimated as the
+    /// change in the proof balance between `proof` and `latest`, fetched just after the
+    /// transaction landed.
+    pub async fn get_mine_reward(
+        &self,
+        signature: &Signature,
+        proof: Proof,
+        latest: Option<Proof>,
+        difficulty: u32,
+    ) -> Option<MineReward> {
+        if let Some(event) = self.get_mine_event(signature).await {
+            return Some(MineReward {
+                difficulty: event.difficulty,
+                reward: event.reward,
+                timing: Some(event.timing),
+                estimated: false,
+            });
+        }
+        let latest = latest?;
+        Some(MineReward {
+            difficulty: difficulty as u64,
+            reward: latest.balance.saturating_sub(proof.balance),
+            timing: None,
+            estimated: true,
+        })
+    }
+
+    async fn get_mine_event(&self, signature: &Signature) -> Option<MineEvent> {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(self.rpc_client.commitment()),
+            max_supported_transaction_version: Some(0),
+        };
+        for _ in 0..FETCH_RETRIES {
+            match self
+                .rpc_client
+                .get_transaction_with_config(signature, config)
+                .await
+            {
+                Ok(tx) => return parse_mine_event(&tx),
+                Err(_) => tokio::time::sleep(FETCH_DELAY).await,
+            }
+        }
+        None
+    }
+}
+
+/// Reads the `MineEvent` the ORE program set as its return data.
+pub fn parse_mine_event(tx: &EncodedConfirmedTransactionWithStatusMeta) -> Option<MineEvent> {
+    let meta = tx.transaction.meta.as_ref()?;
+    let program_id = ore_api::ID.to_string();
+    let data = match &meta.return_data {
+        OptionSerializer::Some(return_data) if return_data.program_id.eq(&program_id) => {
+            return_data.data.0.clone()
+        }
+        // Older RPC nodes only report return data in the logs
+        _ => match &meta.log_messages {
+            OptionSerializer::Some(logs) => logs.iter().rev().find_map(|log| {
+                log.strip_prefix("Program return: ")?
+                    .strip_prefix(program_id.as_str())
+                    .map(|data| data.trim().to_string())
+            })?,
+            _ => return None,
+        },
+    };
+    let bytes = BASE64_STANDARD.decode(data).ok()?;
+    bytemuck::try_pod_read_unaligned::<MineEvent>(&bytes).ok()
+}
@@ -5,7 +5,7 @@ use solana_sdk::signature::Signer;
 
 use crate::{
     args::BalanceArgs,
-    utils::{amount_u64_to_string, get_proof_with_authority},
+    utils::{amount_u64_to_string, get_config, get_proof_with_authority, stake_multiplier},
     Miner,
 };
 
@@ -36,10 +36,12 @@ impl Miner {
         } else {
             "0".to_string()
         };
+        let config = get_config(&self.rpc_client).await;
         println!(
-            "Balance: {} ORE\nStake: {} ORE",
+            "Balance: {} ORE\nStake: {} ORE\nMultiplier: {:.4}x",
             token_balance,
-            amount_u64_to_string(proof.balance)
+            amount_u64_to_string(proof.balance),
+            stake_multiplier(proof.balance, config.top_balance)
         )
     }
 }
@@ -1,62 +1,336 @@
-use std::{sync::Arc, time::Instant};
+use std::{
+    collections::BTreeMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use drillx::equix;
+use serde::Serialize;
 use solana_rpc_client::spinner;
 
-use crate::{args::BenchmarkArgs, Miner};
+use crate::{
+    args::BenchmarkArgs,
+    profile::{profile_path, Profile},
+    Miner,
+};
 
-const TEST_DURATION: i64 = 30;
+/// Fraction of the best sweep throughput a thread count must reach to be recommended.
+const SWEEP_KNEE: f64 = 0.97;
+
+#[derive(Serialize, Debug)]
+pub struct BenchmarkReport {
+    pub threads: u64,
+    pub duration: u64,
+    pub reuse_memory: BenchmarkResult,
+    pub allocate_memory: Option<BenchmarkResult>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BenchmarkResult {
+    pub hashrate: f64,
+    pub hashes: u64,
+    pub per_thread: Vec<ThreadResult>,
+    pub difficulties: BTreeMap<u32, u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ThreadResult {
+    pub thread: u64,
+    pub hashes: u64,
+    pub hashrate: f64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SweepReport {
+    pub duration: u64,
+    pub results: Vec<SweepResult>,
+    pub recommended: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SweepResult {
+    pub threads: u64,
+    pub hashrate: f64,
+}
 
 impl Miner {
     pub async fn benchmark(&self, args: BenchmarkArgs) {
+        if args.sweep {
+            self.benchmark_sweep(args);
+            return;
+        }
+
         // Check num threads
         self.check_num_cores(args.threads);
 
-        // Dispatch job to each thread
-        let challenge = [0; 32];
+        // Measure with solver memory reused across hashes, as the miner does
         let progress_bar = Arc::new(spinner::new_progress_bar());
         progress_bar.set_message(format!(
             "Benchmarking. This will take {} sec...",
-            TEST_DURATION
+            if args.compare_memory {
+                args.duration * 2
+            } else {
+                args.duration
+            }
         ));
-        let handles: Vec<_> = (0..args.threads)
-            .map(|i| {
-                std::thread::spawn({
-                    move || {
-                        let timer = Instant::now();
-                        let first_nonce = u64::MAX.saturating_div(args.threads).saturating_mul(i);
-                        let mut nonce = first_nonce;
-                        loop {
-                            // Create hash
-                            let _hx = drillx::hash(&challenge, &nonce.to_le_bytes());
-
-                            // Increment nonce
-                            nonce += 1;
-
-                            // Exit if time has elapsed
-                            if (timer.elapsed().as_secs() as i64).ge(&TEST_DURATION) {
-                                break;
-                            }
-                        }
-
-                        // Return hash count
-                        nonce - first_nonce
-                    }
+        let reuse_memory = run_benchmark(args.threads, args.duration, true);
+
+        // Optionally measure again allocating solver memory for every hash
+        let allocate_memory = args
+            .compare_memory
+            .then(|| run_benchmark(args.threads, args.duration, false));
+        progress_bar.finish_and_clear();
+
+        let report = BenchmarkReport {
+            threads: args.threads,
+            duration: args.duration,
+            reuse_memory,
+            allocate_memory,
+        };
+        if args.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).expect("Failed to serialize report")
+            );
+            return;
+        }
+
+        // Update log
+        println!("Hashpower: {:.0} H/sec", report.reuse_memory.hashrate);
+        for thread in report.reuse_memory.per_thread.iter() {
+            println!("  Thread {}: {:.0} H/sec", thread.thread, thread.hashrate);
+        }
+        if let Some(allocate_memory) = &report.allocate_memory {
+            println!(
+                "Hashpower without memory reuse: {:.0} H/sec (reuse speedup: {:.2}x)",
+                allocate_memory.hashrate,
+                report.reuse_memory.hashrate / allocate_memory.hashrate.max(f64::EPSILON)
+            );
+        }
+        println!("Difficulty histogram:");
+        for (difficulty, count) in report.reuse_memory.difficulties.iter() {
+            println!("  {:>2}: {}", difficulty, count);
+        }
+    }
+}
+
+impl Miner {
+    fn benchmark_sweep(&self, args: BenchmarkArgs) {
+        let max_threads = args.sweep_max.unwrap_or(num_cpus::get() as u64).max(1);
+        let progress_bar = spinner::new_progress_bar();
+        let mut results = vec![];
+        for threads in 1..=max_threads {
+            progress_bar.set_message(format!(
+                "Benchmarking {} of {} threads. This will take {} sec...",
+                threads,
+                max_threads,
+                (max_threads - threads + 1) * args.duration
+            ));
+            let result = run_benchmark(threads, args.duration, true);
+            results.push(SweepResult {
+                threads,
+                hashrate: result.hashrate,
+            });
+        }
+        progress_bar.finish_and_clear();
+
+        let recommended = sweep_knee(&results);
+        let mut profile = Profile::load();
+        profile.threads = Some(recommended);
+        profile.save().expect("Failed to save profile");
+
+        if args.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&SweepReport {
+                    duration: args.duration,
+                    results,
+                    recommended,
                 })
+                .expect("Failed to serialize report")
+            );
+            return;
+        }
+        for result in results.iter() {
+            println!(
+                "{:>3} threads: {:.0} H/sec ({:.0} H/sec per thread)",
+                result.threads,
+                result.hashrate,
+                result.hashrate / result.threads as f64
+            );
+        }
+        println!(
+            "Recommended: {} threads (saved to {})",
+            recommended,
+            profile_path().display()
+        );
+    }
+}
+
+/// The knee of a sweep: the fewest threads within reach of the best throughput.
+fn sweep_knee(results: &[SweepResult]) -> u64 {
+    let best = results.iter().map(|r| r.hashrate).fold(0f64, f64::max);
+    results
+        .iter()
+        .find(|r| r.hashrate >= best * SWEEP_KNEE)
+        .map(|r| r.threads)
+        .unwrap_or(1)
+}
+
+/// Hashes a fixed challenge on `threads` threads for `duration` seconds.
+pub fn run_benchmark(threads: u64, duration: u64, reuse_memory: bool) -> BenchmarkResult {
+    let challenge = [0; 32];
+    let handles: Vec<_> = (0..threads)
+        .map(|i| {
+            std::thread::spawn(move || {
+                let timer = Instant::now();
+                let mut memory = equix::SolverMemory::new();
+                let mut difficulties = BTreeMap::new();
+                let first_nonce = u64::MAX.saturating_div(threads).saturating_mul(i);
+                let mut nonce = first_nonce;
+                loop {
+                    // Create hash
+                    let hx = if reuse_memory {
+                        drillx::hash_with_memory(&mut memory, &challenge, &nonce.to_le_bytes())
+                    } else {
+                        drillx::hash(&challenge, &nonce.to_le_bytes())
+                    };
+                    if let Ok(hx) = hx {
+                        *difficulties.entry(hx.difficulty()).or_insert(0u64) += 1;
+                    }
+
+                    // Increment nonce
+                    nonce += 1;
+
+                    // Exit if time has elapsed
+                    if timer.elapsed().ge(&Duration::from_secs(duration)) {
+                        break;
+                    }
+                }
+
+                // Return hash count
+                (
+                    nonce - first_nonce,
+                    timer.elapsed().as_secs_f64(),
+                    difficulties,
+                )
             })
-            .collect();
+        })
+        .collect();
 
-        // Join handles and return best nonce
-        let mut total_nonces = 0;
-        for h in handles {
-            if let Ok(count) = h.join() {
-                total_nonces += count;
-            }
+    // Join handles and merge results
+    merge_threads(
+        handles
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, h)| h.join().ok().map(|result| (i as u64, result)))
+            .collect(),
+    )
+}
+
+/// A thread's hash count, elapsed seconds and difficulty histogram.
+type ThreadRun = (u64, f64, BTreeMap<u32, u64>);
+
+/// Merges the runs of each numbered thread.
+fn merge_threads(threads: Vec<(u64, ThreadRun)>) -> BenchmarkResult {
+    let mut per_thread = vec![];
+    let mut difficulties = BTreeMap::new();
+    for (thread, (hashes, elapsed, thread_difficulties)) in threads {
+        per_thread.push(ThreadResult {
+            thread,
+            hashes,
+            hashrate: hashes as f64 / elapsed,
+        });
+        for (difficulty, count) in thread_difficulties {
+            *difficulties.entry(difficulty).or_insert(0) += count;
         }
+    }
+    BenchmarkResult {
+        hashrate: per_thread.iter().map(|t| t.hashrate).sum(),
+        hashes: per_thread.iter().map(|t| t.hashes).sum(),
+        per_thread,
+        difficulties,
+    }
+}
 
-        // Update log
-        progress_bar.finish_with_message(format!(
-            "Hashpower: {} H/sec",
-            total_nonces.saturating_div(TEST_DURATION as u64),
-        ));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_threads_sums_histograms() {
+        let result = merge_threads(vec![
+            (0, (100, 2.0, BTreeMap::from([(1, 60), (2, 40)]))),
+            (1, (50, 1.0, BTreeMap::from([(2, 30), (5, 20)]))),
+        ]);
+        assert_eq!(result.hashes, 150);
+        assert_eq!(result.hashrate, 100.0);
+        assert_eq!(result.per_thread[0].hashrate, 50.0);
+        assert_eq!(result.per_thread[1].thread, 1);
+        assert_eq!(
+            result.difficulties,
+            BTreeMap::from([(1, 60), (2, 70), (5, 20)])
+        );
+    }
+
+    #[test]
+    fn run_benchmark_buckets_solved_hashes() {
+        let result = run_benchmark(2, 1, true);
+        assert_eq!(result.per_thread.len(), 2);
+        assert!(result.hashes > 0);
+
+        // Hashes without a solution are not bucketed
+        let bucketed: u64 = result.difficulties.values().sum();
+        assert!(bucketed > 0 && bucketed <= result.hashes);
+    }
+
+    #[test]
+    fn sweep_knee_picks_fewest_threads_near_the_best() {
+        let sweep = |hashrates: &[f64]| -> Vec<SweepResult> {
+            hashrates
+                .iter()
+                .enumerate()
+                .map(|(i, &hashrate)| SweepResult {
+                    threads: i as u64 + 1,
+                    hashrate,
+                })
+                .collect()
+        };
+
+        // Throughput flattens out after 4 threads
+        assert_eq!(
+            sweep_knee(&sweep(&[100.0, 195.0, 280.0, 390.0, 395.0, 398.0])),
+            4
+        );
+
+        // Throughput drops past the best thread count
+        assert_eq!(sweep_knee(&sweep(&[100.0, 200.0, 300.0, 250.0])), 3);
+        assert_eq!(sweep_knee(&sweep(&[100.0])), 1);
+        assert_eq!(sweep_knee(&[]), 1);
+    }
+
+    #[test]
+    fn report_json_shape() {
+        let report = BenchmarkReport {
+            threads: 1,
+            duration: 2,
+            reuse_memory: merge_threads(vec![(0, (10, 2.0, BTreeMap::from([(3, 10)])))]),
+            allocate_memory: None,
+        };
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "threads": 1,
+                "duration": 2,
+                "reuse_memory": {
+                    "hashrate": 5.0,
+                    "hashes": 10,
+                    "per_thread": [{ "thread": 0, "hashes": 10, "hashrate": 5.0 }],
+                    "difficulties": { "3": 10 },
+                },
+                "allocate_memory": null,
+            })
+        );
     }
 }
@@ -0,0 +1,78 @@
+use std::str::FromStr;
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use colored::*;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
+
+use crate::{
+    args::BroadcastArgs,
+    send_and_confirm::{add_signature, missing_signers},
+    Miner,
+};
+
+impl Miner {
+    pub async fn broadcast(&self, args: BroadcastArgs) {
+        // Accept either encoding printed by --sign-only
+        let encoded = args.transaction.trim();
+        let Some(mut tx) = [
+            BASE64_STANDARD.decode(encoded).ok(),
+            bs58::decode(encoded).into_vec().ok(),
+        ]
+        .into_iter()
+        .flatten()
+        .find_map(|data| bincode::deserialize::<VersionedTransaction>(&data).ok()) else {
+            println!("{} Failed to parse transaction", "ERROR".bold().red());
+            return;
+        };
+
+        // Fill in the signatures of signers which signed elsewhere
+        for signer in args.signers.iter() {
+            let parsed = signer.split_once('=').and_then(|(pubkey, signature)| {
+                Some((
+                    Pubkey::from_str(pubkey).ok()?,
+                    Signature::from_str(signature).ok()?,
+                ))
+            });
+            let Some((pubkey, signature)) = parsed else {
+                println!(
+                    "{} Invalid signer {}, expected PUBKEY=SIGNATURE",
+                    "ERROR".bold().red(),
+                    signer
+                );
+                return;
+            };
+            if !add_signature(&mut tx, pubkey, signature) {
+                println!(
+                    "{} Transaction doesn't need a signature from {}",
+                    "ERROR".bold().red(),
+                    pubkey
+                );
+                return;
+            }
+        }
+
+        // Only a complete transaction can be submitted
+        let missing = missing_signers(&tx);
+        if !missing.is_empty() {
+            println!(
+                "{} Transaction is missing signatures from: {}",
+                "ERROR".bold().red(),
+                missing
+                    .iter()
+                    .map(|pubkey| pubkey.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            return;
+        }
+        if tx.verify_with_results().iter().any(|verified| !verified) {
+            println!(
+                "{} Transaction has an invalid signature",
+                "ERROR".bold().red()
+            );
+            return;
+        }
+        self.submit_and_confirm(&tx, false).await.ok();
+    }
+}
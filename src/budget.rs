@@ -0,0 +1,200 @@
+use chrono::{Local, NaiveDate};
+use colored::*;
+use solana_program::{native_token::sol_to_lamports, pubkey::Pubkey};
+use solana_sdk::transaction::VersionedTransaction;
+
+use crate::{
+    args::MineArgs,
+    estimate::message_fee,
+    history::{fees_path, local_date, read_records, FeeRecord},
+    tui::print_line,
+    Miner,
+};
+
+/// Caps on the SOL `ore mine` spends on transaction fees.
+pub struct Budget {
+    /// Most lamports to spend this session.
+    session: Option<u64>,
+
+    /// Most lamports to spend per day, in local time.
+    daily: Option<u64>,
+    spent_session: u64,
+    spent_today: u64,
+    today: NaiveDate,
+}
+
+/// Which budget a fee would go over.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Overspend {
+    Session,
+    Daily,
+
+    /// The fee alone is more than the daily budget, so waiting a day wouldn't help.
+    Fee,
+}
+
+impl Budget {
+    /// Reads the budgets from the mining args, or `None` if none are set.
+    ///
+    /// Fees the payers already paid today, as recorded in the fee history, count against
+    /// the daily budget.
+    pub fn from_args(payers: &[Pubkey], args: &MineArgs) -> Option<Self> {
+        if args.session_budget.is_none() && args.daily_budget.is_none() {
+            return None;
+        }
+        let mut budget = Self::new(
+            args.session_budget.map(sol_to_lamports),
+            args.daily_budget.map(sol_to_lamports),
+            Local::now().date_naive(),
+        );
+        budget.spent_today = spent_on(payers, budget.today);
+        Some(budget)
+    }
+
+    fn new(session: Option<u64>, daily: Option<u64>, today: NaiveDate) -> Self {
+        Self {
+            session,
+            daily,
+            spent_session: 0,
+            spent_today: 0,
+            today,
+        }
+    }
+
+    /// Checks the fee fits in both budgets.
+    pub fn check(&mut self, fee: u64) -> Result<(), Overspend> {
+        self.roll_over(Local::now().date_naive());
+        if self
+            .session
+            .is_some_and(|session| self.spent_session.saturating_add(fee).gt(&session))
+        {
+            return Err(Overspend::Session);
+        }
+        if let Some(daily) = self.daily {
+            if fee.gt(&daily) {
+                return Err(Overspend::Fee);
+            }
+            if self.spent_today.saturating_add(fee).gt(&daily) {
+                return Err(Overspend::Daily);
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts a fee against both budgets.
+    pub fn spend(&mut self, fee: u64) {
+        self.roll_over(Local::now().date_naive());
+        self.spent_session = self.spent_session.saturating_add(fee);
+        self.spent_today = self.spent_today.saturating_add(fee);
+    }
+
+    /// Starts a new daily budget once the day changes.
+    fn roll_over(&mut self, today: NaiveDate) {
+        if today.ne(&self.today) {
+            self.today = today;
+            self.spent_today = 0;
+        }
+    }
+}
+
+impl Miner {
+    /// Records the fee of a transaction which landed, successfully or not, and counts it
+    /// against the budgets.
+    pub fn record_fee(&self, tx: &VersionedTransaction) {
+        let fee = message_fee(&tx.message);
+        if let Some(budget) = self.budget.get() {
+            budget.lock().unwrap().spend(fee);
+        }
+        let record = FeeRecord {
+            timestamp: chrono::Utc::now().timestamp(),
+            payer: tx.message.static_account_keys()[0].to_string(),
+            signature: tx.signatures[0].to_string(),
+            fee,
+        };
+        if let Err(err) = record.save() {
+            print_line(format!(
+                "{} Failed to record fee in {}: {}",
+                "WARNING".bold().yellow(),
+                fees_path().display(),
+                err
+            ));
+        }
+    }
+
+    /// Checks the fee fits in the budgets, if `ore mine` was started with any.
+    pub fn check_budget(&self, fee: u64) -> Result<(), Overspend> {
+        match self.budget.get() {
+            Some(budget) => budget.lock().unwrap().check(fee),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Fees the payers paid on a day, in local time.
+fn spent_on(payers: &[Pubkey], date: NaiveDate) -> u64 {
+    let payers: Vec<String> = payers.iter().map(Pubkey::to_string).collect();
+    read_records::<FeeRecord>(&fees_path())
+        .iter()
+        .filter(|record| payers.contains(&record.payer) && local_date(record.timestamp).eq(&date))
+        .map(|record| record.fee)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        Local::now().date_naive()
+    }
+
+    #[test]
+    fn check_and_spend_session() {
+        let mut budget = Budget::new(Some(10_000), None, today());
+        assert_eq!(budget.check(10_000), Ok(()));
+        budget.spend(6000);
+        assert_eq!(budget.check(4000), Ok(()));
+        assert_eq!(budget.check(4001), Err(Overspend::Session));
+        budget.spend(4000);
+        assert_eq!(budget.check(1), Err(Overspend::Session));
+    }
+
+    #[test]
+    fn check_and_spend_daily() {
+        let mut budget = Budget::new(None, Some(10_000), today());
+        assert_eq!(budget.check(10_001), Err(Overspend::Fee));
+        budget.spend(8000);
+        assert_eq!(budget.check(2000), Ok(()));
+        assert_eq!(budget.check(2001), Err(Overspend::Daily));
+
+        // A fee larger than the whole daily budget is reported as such, not as a wait
+        assert_eq!(budget.check(20_000), Err(Overspend::Fee));
+    }
+
+    #[test]
+    fn session_budget_is_checked_first() {
+        let mut budget = Budget::new(Some(5000), Some(10_000), today());
+        budget.spend(5000);
+        assert_eq!(budget.check(1), Err(Overspend::Session));
+    }
+
+    #[test]
+    fn roll_over_resets_only_the_daily_spend() {
+        let yesterday = today().pred_opt().unwrap();
+        let mut budget = Budget::new(Some(20_000), Some(10_000), yesterday);
+        budget.spent_session = 9000;
+        budget.spent_today = 9000;
+
+        budget.roll_over(yesterday);
+        assert_eq!(budget.spent_today, 9000);
+        budget.roll_over(today());
+        assert_eq!(budget.spent_today, 0);
+        assert_eq!(budget.spent_session, 9000);
+        assert_eq!(budget.today, today());
+
+        // Checking a fee rolls the day over by itself
+        let mut budget = Budget::new(None, Some(10_000), yesterday);
+        budget.spent_today = 10_000;
+        assert_eq!(budget.check(10_000), Ok(()));
+    }
+}
@@ -11,12 +11,9 @@ impl Miner {
         let client = self.rpc_client.clone();
         for address in BUS_ADDRESSES.iter() {
             let data = client.get_account_data(address).await.unwrap();
-            match Bus::try_from_bytes(&data) {
-                Ok(bus) => {
-                    let rewards = (bus.rewards as f64) / 10f64.powf(TOKEN_DECIMALS as f64);
-                    println!("Bus {}: {:} ORE", bus.id, rewards);
-                }
-                Err(_) => {}
+            if let Ok(bus) = Bus::try_from_bytes(&data) {
+                let rewards = (bus.rewards as f64) / 10f64.powf(TOKEN_DECIMALS as f64);
+                println!("Bus {}: {:} ORE", bus.id, rewards);
             }
         }
     }
@@ -1,23 +1,82 @@
-use std::str::FromStr;
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
 use colored::*;
-use ore_api::consts::MINT_ADDRESS;
-use solana_program::pubkey::Pubkey;
-use solana_sdk::signature::Signer;
+use ore_api::{
+    consts::{MINT_ADDRESS, TOKEN_DECIMALS},
+    state::Proof,
+};
+use ore_utils::AccountDeserialize;
+use solana_client::{client_error::Result as ClientResult, rpc_request::MAX_MULTIPLE_ACCOUNTS};
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::{
+    message::Message,
+    packet::PACKET_DATA_SIZE,
+    signature::{read_keypair_file, Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
 use spl_token::amount_to_ui_amount;
 
 use crate::{
     args::ClaimArgs,
-    cu_limits::CU_LIMIT_CLAIM,
+    cu_limits::{CU_LIMIT_ATA, CU_LIMIT_CLAIM},
+    history::{claims_path, ClaimRecord},
+    keystore::{read_password, Keystore},
+    notify::Event,
     send_and_confirm::ComputeBudget,
-    utils::{amount_f64_to_u64, ask_confirm, get_proof_with_authority},
+    utils::{amount_f64_to_u64, ask_confirm, get_proof_with_authority, proof_pubkey},
     Miner,
 };
 
 impl Miner {
-    pub async fn claim(&self, args: ClaimArgs) {
+    /// Claims rewards, asking first unless the caller already confirmed the claim.
+    pub async fn claim(&self, args: ClaimArgs, confirmed: bool) {
+        if let Some(dir) = &args.keypairs {
+            self.claim_batch(dir, &args).await;
+            return;
+        }
+
         let signer = self.signer();
         let pubkey = signer.pubkey();
+
+        // Sign-only mode must not depend on chain state
+        if args.sign.sign_only {
+            let Some(amount) = args.amount else {
+                println!(
+                    "{} --amount is required with --sign-only",
+                    "ERROR".bold().red()
+                );
+                return;
+            };
+            let wallet = match args.to {
+                Some(to) => Pubkey::from_str(&to).expect("Failed to parse wallet address"),
+                None => pubkey,
+            };
+            let beneficiary =
+                spl_associated_token_account::get_associated_token_address(&wallet, &MINT_ADDRESS);
+            let ixs = [
+                create_associated_token_account_idempotent(
+                    &pubkey,
+                    &wallet,
+                    &MINT_ADDRESS,
+                    &spl_token::id(),
+                ),
+                ore_api::instruction::claim(pubkey, beneficiary, amount_f64_to_u64(amount)),
+            ];
+            self.send_or_sign(
+                &ixs,
+                ComputeBudget::Fixed(CU_LIMIT_CLAIM + CU_LIMIT_ATA),
+                &args.sign,
+            )
+            .await
+            .ok();
+            return;
+        }
+
         let proof = get_proof_with_authority(&self.rpc_client, pubkey).await;
         let mut ixs = vec![];
         let beneficiary = match args.to {
@@ -52,29 +111,81 @@ impl Miner {
         let amount = if let Some(amount) = args.amount {
             amount_f64_to_u64(amount)
         } else {
-            proof.balance
+            proof
+                .balance
+                .saturating_sub(amount_f64_to_u64(args.reserve))
         };
+        if amount == 0 {
+            println!("Nothing to claim");
+            return;
+        }
 
         // Confirm user wants to claim
-        if !ask_confirm(
-            format!(
-                "\nYou are about to claim {}.\n\nAre you sure you want to continue? [Y/n]",
+        if !self.yes
+            && !confirmed
+            && !ask_confirm(
                 format!(
-                    "{} ORE",
-                    amount_to_ui_amount(amount, ore_api::consts::TOKEN_DECIMALS)
+                    "\nYou are about to claim {}.\n\nAre you sure you want to continue?",
+                    format!(
+                        "{} ORE",
+                        amount_to_ui_amount(amount, ore_api::consts::TOKEN_DECIMALS)
+                    )
+                    .bold(),
                 )
-                .bold(),
+                .as_str(),
+                true,
             )
-            .as_str(),
-        ) {
+        {
             return;
         }
 
         // Send and confirm
         ixs.push(ore_api::instruction::claim(pubkey, beneficiary, amount));
-        self.send_and_confirm(&ixs, ComputeBudget::Fixed(CU_LIMIT_CLAIM), false)
+        if let Ok(sig) = self
+            .send_or_sign(&ixs, ComputeBudget::Fixed(CU_LIMIT_CLAIM), &args.sign)
             .await
-            .ok();
+        {
+            if !args.sign.sign_only {
+                record_claim(pubkey, beneficiary, amount, sig, "manual");
+            }
+        }
+    }
+
+    /// Claims `amount` to `wallet`'s token account without asking, and records it in the
+    /// claim history.
+    pub async fn send_claim(
+        &self,
+        wallet: Pubkey,
+        amount: u64,
+        trigger: &str,
+    ) -> ClientResult<Signature> {
+        let pubkey = self.signer().pubkey();
+        let beneficiary =
+            spl_associated_token_account::get_associated_token_address(&wallet, &MINT_ADDRESS);
+        let ixs = [
+            create_associated_token_account_idempotent(
+                &pubkey,
+                &wallet,
+                &MINT_ADDRESS,
+                &spl_token::id(),
+            ),
+            ore_api::instruction::claim(pubkey, beneficiary, amount),
+        ];
+        let sig = self
+            .send_and_confirm(
+                &ixs,
+                ComputeBudget::Fixed(CU_LIMIT_CLAIM + CU_LIMIT_ATA),
+                false,
+            )
+            .await?;
+        record_claim(pubkey, beneficiary, amount, sig, trigger);
+        self.notify(Event::ClaimCompleted {
+            amount,
+            beneficiary: wallet.to_string(),
+            signature: sig.to_string(),
+            trigger: trigger.to_string(),
+        });
+        Ok(sig)
     }
 
     async fn initialize_ata(&self) -> Pubkey {
@@ -107,3 +218,331 @@ impl Miner {
         token_account_pubkey
     }
 }
+
+/// Number of times to send the claims of a batch, the first in packed transactions and
+/// the rest one claim per transaction.
+const BATCH_CLAIM_ATTEMPTS: usize = 3;
+
+/// How long to wait before retrying the claims which failed.
+const BATCH_CLAIM_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// One wallet's claim in a batch.
+struct BatchClaim {
+    keypair: Keypair,
+    amount: u64,
+    status: ClaimStatus,
+}
+
+enum ClaimStatus {
+    NoProof,
+    NothingToClaim,
+    Pending,
+    Failed(String),
+    Claimed(Signature),
+}
+
+impl Miner {
+    /// Claims from every keypair in `dir` into one wallet, packing as many claims into each
+    /// transaction as fit and retrying the ones that fail.
+    async fn claim_batch(&self, dir: &str, args: &ClaimArgs) {
+        let payer = self.signer();
+        let wallet = match &args.to {
+            Some(to) => Pubkey::from_str(to).expect("Failed to parse wallet address"),
+            None => payer.pubkey(),
+        };
+        let beneficiary =
+            spl_associated_token_account::get_associated_token_address(&wallet, &MINT_ADDRESS);
+
+        // Read keypairs, skipping files which aren't one
+        let mut paths: Vec<_> = std::fs::read_dir(dir)
+            .expect("Failed to read keypair directory")
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|extension| extension.eq("json"))
+            })
+            .collect();
+        paths.sort();
+        let mut password = None;
+        let mut claims = vec![];
+        let mut skipped = vec![];
+        for path in paths {
+            match self.read_batch_keypair(&path, &mut password) {
+                Ok(keypair) => claims.push(BatchClaim {
+                    keypair,
+                    amount: 0,
+                    status: ClaimStatus::NoProof,
+                }),
+                Err(err) => skipped.push((path, err)),
+            }
+        }
+        if claims.is_empty() {
+            println!("No keypairs found in {}", dir);
+            print_skipped(&skipped);
+            return;
+        }
+
+        // Fetch every proof in as few requests as possible
+        let reserve = amount_f64_to_u64(args.reserve);
+        for chunk in claims.chunks_mut(MAX_MULTIPLE_ACCOUNTS) {
+            let addresses: Vec<Pubkey> = chunk
+                .iter()
+                .map(|claim| proof_pubkey(claim.keypair.pubkey()))
+                .collect();
+            let accounts = self
+                .rpc_client
+                .get_multiple_accounts(&addresses)
+                .await
+                .expect("Failed to fetch proof accounts");
+            for (claim, account) in chunk.iter_mut().zip(accounts) {
+                let Some(proof) = account
+                    .as_ref()
+                    .and_then(|account| Proof::try_from_bytes(&account.data).ok())
+                else {
+                    continue;
+                };
+                claim.amount = proof.balance.saturating_sub(reserve);
+                claim.status = if claim.amount > 0 {
+                    ClaimStatus::Pending
+                } else {
+                    ClaimStatus::NothingToClaim
+                };
+            }
+        }
+
+        // Confirm user wants to claim
+        let pending = claims
+            .iter()
+            .filter(|claim| matches!(claim.status, ClaimStatus::Pending))
+            .count();
+        let total: u64 = claims.iter().map(|claim| claim.amount).sum();
+        if pending == 0 {
+            println!("Nothing to claim");
+            print_skipped(&skipped);
+            return;
+        }
+        if !self.yes
+            && !ask_confirm(
+                format!(
+                    "\nYou are about to claim {} from {} wallets to {}.\n\nAre you sure you want to continue?",
+                    format!("{} ORE", amount_to_ui_amount(total, TOKEN_DECIMALS)).bold(),
+                    pending,
+                    wallet
+                )
+                .as_str(),
+                true,
+            )
+        {
+            return;
+        }
+
+        // Send claims, retrying only the ones which failed, each on its own so one bad wallet
+        // can't fail the rest of its transaction again
+        for attempt in 0..BATCH_CLAIM_ATTEMPTS {
+            let unclaimed: Vec<usize> = (0..claims.len())
+                .filter(|&i| {
+                    matches!(
+                        claims[i].status,
+                        ClaimStatus::Pending | ClaimStatus::Failed(_)
+                    )
+                })
+                .collect();
+            if unclaimed.is_empty() {
+                break;
+            }
+            let batches = if attempt == 0 {
+                self.pack_claims(&payer, wallet, beneficiary, &claims, &unclaimed)
+            } else {
+                println!("Retrying {} failed claims one by one", unclaimed.len());
+                tokio::time::sleep(BATCH_CLAIM_RETRY_DELAY).await;
+                unclaimed.iter().map(|&i| vec![i]).collect()
+            };
+            for batch in batches {
+                let ixs = claim_batch_ixs(&payer, wallet, beneficiary, &claims, &batch);
+                let final_ixs = self.build_instructions(
+                    &ixs,
+                    ComputeBudget::Fixed(batch_compute_budget(batch.len())),
+                    None,
+                );
+                let mut signers = vec![&payer];
+                for &i in batch.iter() {
+                    if claims[i].keypair.pubkey().ne(&payer.pubkey()) {
+                        signers.push(&claims[i].keypair);
+                    }
+                }
+                let result = match self
+                    .rpc_client
+                    .get_latest_blockhash_with_commitment(self.rpc_client.commitment())
+                    .await
+                {
+                    Ok((hash, _)) => {
+                        let tx = self.sign_transaction(&final_ixs, &signers, hash).await;
+                        self.submit_and_confirm(&tx, false).await
+                    }
+                    Err(err) => Err(err),
+                };
+                for &i in batch.iter() {
+                    claims[i].status = match &result {
+                        Ok(sig) => {
+                            record_claim(
+                                claims[i].keypair.pubkey(),
+                                beneficiary,
+                                claims[i].amount,
+                                *sig,
+                                "batch",
+                            );
+                            ClaimStatus::Claimed(*sig)
+                        }
+                        Err(err) => ClaimStatus::Failed(err.to_string()),
+                    };
+                }
+            }
+        }
+
+        // Print summary
+        println!("\n{:<44}  {:>20}  Status", "Wallet", "Amount (ORE)");
+        let mut claimed = 0;
+        for claim in claims.iter() {
+            let status = match &claim.status {
+                ClaimStatus::NoProof => "No proof account".dimmed().to_string(),
+                ClaimStatus::NothingToClaim => "Nothing to claim".dimmed().to_string(),
+                ClaimStatus::Pending => "Not sent".yellow().to_string(),
+                ClaimStatus::Failed(err) => format!("{} {}", "Failed".red(), err),
+                ClaimStatus::Claimed(sig) => {
+                    claimed += claim.amount;
+                    format!("{} {}", "Claimed".green(), sig)
+                }
+            };
+            println!(
+                "{:<44}  {:>20}  {}",
+                claim.keypair.pubkey(),
+                amount_to_ui_amount(claim.amount, TOKEN_DECIMALS),
+                status
+            );
+        }
+        print_skipped(&skipped);
+        println!(
+            "\nClaimed {} of {} ORE to {}",
+            amount_to_ui_amount(claimed, TOKEN_DECIMALS),
+            amount_to_ui_amount(total, TOKEN_DECIMALS),
+            wallet
+        );
+    }
+
+    /// Reads a plaintext keypair or a keystore for a batch claim, asking for the keystore
+    /// password only once and reusing it for the rest of the batch.
+    fn read_batch_keypair(
+        &self,
+        path: &Path,
+        password: &mut Option<String>,
+    ) -> Result<Keypair, String> {
+        match Keystore::read(path) {
+            Some(keystore) => {
+                let password = password
+                    .get_or_insert_with(|| read_password(&self.password_file, "the keystores"));
+                keystore.decrypt(password)
+            }
+            None => read_keypair_file(path).map_err(|_| "Not a keypair file".to_string()),
+        }
+    }
+
+    /// Splits the claims into groups that each fit in one transaction.
+    fn pack_claims(
+        &self,
+        payer: &Keypair,
+        wallet: Pubkey,
+        beneficiary: Pubkey,
+        claims: &[BatchClaim],
+        unclaimed: &[usize],
+    ) -> Vec<Vec<usize>> {
+        let mut batches: Vec<Vec<usize>> = vec![];
+        let mut batch = vec![];
+        for &i in unclaimed {
+            batch.push(i);
+            let ixs = claim_batch_ixs(payer, wallet, beneficiary, claims, &batch);
+            let final_ixs = self.build_instructions(
+                &ixs,
+                ComputeBudget::Fixed(batch_compute_budget(batch.len())),
+                None,
+            );
+            let tx = Transaction::new_unsigned(Message::new(&final_ixs, Some(&payer.pubkey())));
+            let fits =
+                bincode::serialized_size(&tx).is_ok_and(|size| size as usize <= PACKET_DATA_SIZE);
+            if !fits && batch.len() > 1 {
+                batch.pop();
+                batches.push(std::mem::replace(&mut batch, vec![i]));
+            }
+        }
+        if !batch.is_empty() {
+            batches.push(batch);
+        }
+        batches
+    }
+}
+
+/// Lists the files in the keypair directory which could not be read.
+fn print_skipped(skipped: &[(PathBuf, String)]) {
+    if skipped.is_empty() {
+        return;
+    }
+    println!("\n{}", "Skipped files".bold());
+    for (path, err) in skipped {
+        println!("  {}: {}", path.display(), err);
+    }
+}
+
+/// Instructions to create the beneficiary token account, if needed, and claim for each
+/// wallet in the batch.
+fn claim_batch_ixs(
+    payer: &Keypair,
+    wallet: Pubkey,
+    beneficiary: Pubkey,
+    claims: &[BatchClaim],
+    batch: &[usize],
+) -> Vec<Instruction> {
+    let mut ixs = vec![create_associated_token_account_idempotent(
+        &payer.pubkey(),
+        &wallet,
+        &MINT_ADDRESS,
+        &spl_token::id(),
+    )];
+    for &i in batch {
+        ixs.push(ore_api::instruction::claim(
+            claims[i].keypair.pubkey(),
+            beneficiary,
+            claims[i].amount,
+        ));
+    }
+    ixs
+}
+
+fn batch_compute_budget(claims: usize) -> u32 {
+    CU_LIMIT_ATA + CU_LIMIT_CLAIM * claims as u32
+}
+
+fn record_claim(
+    authority: Pubkey,
+    beneficiary: Pubkey,
+    amount: u64,
+    signature: Signature,
+    trigger: &str,
+) {
+    let record = ClaimRecord {
+        timestamp: chrono::Utc::now().timestamp(),
+        authority: authority.to_string(),
+        beneficiary: beneficiary.to_string(),
+        amount,
+        signature: signature.to_string(),
+        trigger: trigger.to_string(),
+    };
+    if let Err(err) = record.save() {
+        println!(
+            "{} Failed to record claim in {}: {}",
+            "WARNING".bold().yellow(),
+            claims_path().display(),
+            err
+        );
+    }
+}
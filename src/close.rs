@@ -3,41 +3,60 @@ use solana_sdk::signature::Signer;
 use spl_token::amount_to_ui_amount;
 
 use crate::{
-    args::ClaimArgs,
+    args::{ClaimArgs, CloseArgs},
+    cu_limits::CU_LIMIT_CLOSE,
     send_and_confirm::ComputeBudget,
     utils::{ask_confirm, get_proof_with_authority},
     Miner,
 };
 
 impl Miner {
-    pub async fn close(&self) {
-        // Confirm proof exists
+    pub async fn close(&self, args: CloseArgs) {
+        // Sign-only mode can't check the stake, so it must already be claimed
         let signer = self.signer();
+        if args.sign.sign_only {
+            let ix = ore_api::instruction::close(signer.pubkey());
+            self.send_or_sign(&[ix], ComputeBudget::Fixed(CU_LIMIT_CLOSE), &args.sign)
+                .await
+                .ok();
+            return;
+        }
+
+        // Confirm proof exists
         let proof = get_proof_with_authority(&self.rpc_client, signer.pubkey()).await;
 
         // Confirm the user wants to close.
-        if !ask_confirm(
-            format!("{} You have {} ORE staked in this account.\nAre you sure you want to {}close this account? [Y/n]", 
-                "WARNING".yellow(),
-                amount_to_ui_amount(proof.balance, ore_api::consts::TOKEN_DECIMALS),
-                if proof.balance.gt(&0) { "claim your stake and "} else { "" }
-            ).as_str()
-        ) {
+        if !self.yes
+            && !ask_confirm(
+                format!("{} You have {} ORE staked in this account.\nAre you sure you want to {}close this account?",
+                    "WARNING".yellow(),
+                    amount_to_ui_amount(proof.balance, ore_api::consts::TOKEN_DECIMALS),
+                    if proof.balance.gt(&0) { "claim your stake and "} else { "" }
+                ).as_str(),
+                false,
+            )
+        {
             return;
         }
 
         // Claim stake
         if proof.balance.gt(&0) {
-            self.claim(ClaimArgs {
-                amount: None,
-                to: None,
-            })
+            self.claim(
+                ClaimArgs {
+                    amount: None,
+                    to: None,
+                    reserve: 0.0,
+                    keypairs: None,
+                    sign: args.sign.clone(),
+                },
+                true,
+            )
             .await;
         }
 
         // Submit close transaction
         let ix = ore_api::instruction::close(signer.pubkey());
-        self.send_and_confirm(&[ix], ComputeBudget::Dynamic, false)
+        self.send_or_sign(&[ix], ComputeBudget::Fixed(CU_LIMIT_CLOSE), &args.sign)
             .await
             .ok();
     }
@@ -0,0 +1,377 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use colored::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+};
+
+use crate::{
+    args::{CtlArgs, CtlCommand},
+    utils::ore_dir,
+    Miner,
+};
+
+/// JSON-RPC error codes.
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Mining settings which can be changed over the control socket while the miner runs.
+///
+/// The mining loop reads them at the start of each round, so changes apply from the
+/// next round.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ControlState {
+    pub paused: bool,
+    pub threads: u64,
+    pub min_difficulty: u32,
+    pub priority_fee: u64,
+
+    /// Set when a claim was requested and not yet sent.
+    pub claim_requested: bool,
+}
+
+pub type SharedControlState = Arc<Mutex<ControlState>>;
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl Miner {
+    /// Sends a command to a running miner and prints its reply.
+    pub async fn ctl(&self, args: CtlArgs) {
+        let (method, params) = match args.command {
+            CtlCommand::Status => ("status", Value::Null),
+            CtlCommand::Pause => ("pause", Value::Null),
+            CtlCommand::Resume => ("resume", Value::Null),
+            CtlCommand::Threads { threads } => ("set_threads", json!({ "threads": threads })),
+            CtlCommand::Difficulty { difficulty } => {
+                ("set_min_difficulty", json!({ "difficulty": difficulty }))
+            }
+            CtlCommand::PriorityFee { priority_fee } => {
+                ("set_priority_fee", json!({ "priority_fee": priority_fee }))
+            }
+            CtlCommand::Claim => ("claim", Value::Null),
+        };
+        let path = args
+            .control_socket
+            .map_or_else(default_control_socket, PathBuf::from);
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        let response = match call(&path, &request).await {
+            Ok(response) => response,
+            Err(err) => {
+                eprintln!(
+                    "{} Failed to reach the miner at {}: {}",
+                    "ERROR".bold().red(),
+                    path.display(),
+                    err
+                );
+                std::process::exit(1);
+            }
+        };
+        if let Some(error) = response.get("error") {
+            let message = error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("Unknown error");
+            eprintln!("{} {}", "ERROR".bold().red(), message);
+            std::process::exit(1);
+        }
+        match response.get("result") {
+            Some(Value::String(message)) => println!("{}", message),
+            Some(result) => println!(
+                "{}",
+                serde_json::to_string_pretty(result).unwrap_or_default()
+            ),
+            None => {}
+        }
+    }
+}
+
+/// The control socket's file, removed when dropped.
+pub struct ControlSocket {
+    path: PathBuf,
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+/// Listens for JSON-RPC requests on a Unix socket, one per line, and applies them to
+/// the shared state.
+pub fn serve(path: &Path, state: SharedControlState) -> std::io::Result<ControlSocket> {
+    remove_stale_socket(path)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle_connection(stream, state.clone()));
+        }
+    });
+    Ok(ControlSocket {
+        path: path.to_path_buf(),
+    })
+}
+
+/// Removes a socket left behind by a miner which exited without cleaning up.
+///
+/// Refuses to touch anything which is not a socket, or a socket a miner still listens on.
+fn remove_stale_socket(path: &Path) -> std::io::Result<()> {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            "the path exists and is not a socket",
+        ));
+    }
+    if std::os::unix::net::UnixStream::connect(path).is_ok() {
+        return Err(Error::new(
+            ErrorKind::AddrInUse,
+            "the socket is already in use by another miner",
+        ));
+    }
+    fs::remove_file(path)
+}
+
+pub fn default_control_socket() -> PathBuf {
+    ore_dir().join("mine.sock")
+}
+
+async fn handle_connection(stream: UnixStream, state: SharedControlState) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                let result = handle_request(&request, &state);
+                Response {
+                    jsonrpc: "2.0",
+                    id: request.id,
+                    result: result.as_ref().ok().cloned(),
+                    error: result.err(),
+                }
+            }
+            Err(err) => Response {
+                jsonrpc: "2.0",
+                id: Value::Null,
+                result: None,
+                error: Some(RpcError {
+                    code: PARSE_ERROR,
+                    message: format!("Invalid request: {}", err),
+                }),
+            },
+        };
+        let Ok(mut response) = serde_json::to_string(&response) else {
+            break;
+        };
+        response.push('\n');
+        if writer.write_all(response.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
+
+fn handle_request(request: &Request, state: &SharedControlState) -> Result<Value, RpcError> {
+    let mut state = state.lock().unwrap();
+    let message = match request.method.as_str() {
+        "status" => {
+            return Ok(serde_json::to_value(&*state).unwrap_or_default());
+        }
+        "pause" => {
+            state.paused = true;
+            "Mining will pause after the current round".to_string()
+        }
+        "resume" => {
+            state.paused = false;
+            "Mining resumed".to_string()
+        }
+        "set_threads" => {
+            let threads = param(request, "threads")?;
+            if threads == 0 {
+                return Err(invalid_params("threads must be at least 1"));
+            }
+            state.threads = threads;
+            format!("Threads set to {} from the next round", threads)
+        }
+        "set_min_difficulty" => {
+            let difficulty = param(request, "difficulty")?;
+            state.min_difficulty =
+                u32::try_from(difficulty).map_err(|_| invalid_params("difficulty is too large"))?;
+            format!(
+                "Minimum difficulty set to {} from the next round",
+                difficulty
+            )
+        }
+        "set_priority_fee" => {
+            let priority_fee = param(request, "priority_fee")?;
+            state.priority_fee = priority_fee;
+            format!(
+                "Priority fee set to {} microlamports from the next round",
+                priority_fee
+            )
+        }
+        "claim" => {
+            state.claim_requested = true;
+            if state.paused {
+                "Rewards will be claimed while paused".to_string()
+            } else {
+                "Rewards will be claimed after the current round".to_string()
+            }
+        }
+        method => {
+            return Err(RpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("Unknown method `{}`", method),
+            });
+        }
+    };
+    Ok(Value::String(message))
+}
+
+/// Reads a named integer parameter.
+fn param(request: &Request, name: &str) -> Result<u64, RpcError> {
+    request
+        .params
+        .get(name)
+        .and_then(Value::as_u64)
+        .ok_or_else(|| invalid_params(&format!("expected an integer `{}` param", name)))
+}
+
+fn invalid_params(message: &str) -> RpcError {
+    RpcError {
+        code: INVALID_PARAMS,
+        message: format!("Invalid params: {}", message),
+    }
+}
+
+/// Sends one request and waits for its response.
+async fn call(path: &Path, request: &Value) -> std::io::Result<Value> {
+    let stream = UnixStream::connect(path).await?;
+    let (reader, mut writer) = stream.into_split();
+    writer
+        .write_all(format!("{}\n", request).as_bytes())
+        .await?;
+    let line = BufReader::new(reader)
+        .lines()
+        .next_line()
+        .await?
+        .unwrap_or_default();
+    serde_json::from_str(&line).map_err(std::io::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> SharedControlState {
+        Arc::new(Mutex::new(ControlState {
+            paused: false,
+            threads: 4,
+            min_difficulty: 8,
+            priority_fee: 0,
+            claim_requested: false,
+        }))
+    }
+
+    fn request(method: &str, params: Value) -> Request {
+        Request {
+            id: json!(1),
+            method: method.to_string(),
+            params,
+        }
+    }
+
+    #[test]
+    fn handle_request_updates_state() {
+        let state = state();
+        handle_request(&request("pause", Value::Null), &state).unwrap();
+        assert!(state.lock().unwrap().paused);
+        handle_request(&request("resume", Value::Null), &state).unwrap();
+        assert!(!state.lock().unwrap().paused);
+        handle_request(&request("set_threads", json!({ "threads": 2 })), &state).unwrap();
+        handle_request(
+            &request("set_min_difficulty", json!({ "difficulty": 12 })),
+            &state,
+        )
+        .unwrap();
+        handle_request(
+            &request("set_priority_fee", json!({ "priority_fee": 5000 })),
+            &state,
+        )
+        .unwrap();
+        handle_request(&request("claim", Value::Null), &state).unwrap();
+
+        let status = handle_request(&request("status", Value::Null), &state).unwrap();
+        assert_eq!(
+            status,
+            json!({
+                "paused": false,
+                "threads": 2,
+                "min_difficulty": 12,
+                "priority_fee": 5000,
+                "claim_requested": true,
+            })
+        );
+    }
+
+    #[test]
+    fn handle_request_errors() {
+        let state = state();
+        let code = |method: &str, params: Value| {
+            handle_request(&request(method, params), &state)
+                .unwrap_err()
+                .code
+        };
+        assert_eq!(code("stop", Value::Null), METHOD_NOT_FOUND);
+        assert_eq!(code("set_threads", Value::Null), INVALID_PARAMS);
+        assert_eq!(code("set_threads", json!({ "threads": 0 })), INVALID_PARAMS);
+        assert_eq!(
+            code("set_threads", json!({ "threads": "2" })),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code("set_min_difficulty", json!({ "difficulty": u64::MAX })),
+            INVALID_PARAMS
+        );
+        assert_eq!(state.lock().unwrap().threads, 4);
+    }
+}
@@ -0,0 +1,327 @@
+use colored::*;
+use ore_api::{
+    consts::{ONE_MINUTE, TOKEN_DECIMALS},
+    state::Config,
+};
+use solana_program::borsh1::try_from_slice_unchecked;
+use solana_rpc_client::spinner;
+use solana_sdk::{
+    compute_budget::{self, ComputeBudgetInstruction},
+    message::VersionedMessage,
+    native_token::{lamports_to_sol, sol_to_lamports},
+    signature::Signer,
+};
+
+use crate::{
+    args::{EstimateArgs, Threads},
+    benchmark::run_benchmark,
+    cu_limits::CU_LIMIT_SUBMIT,
+    mine::resolve_threads,
+    utils::{
+        amount_f64_to_u64, amount_u64_to_f64, get_config, stake_multiplier,
+        try_get_proof_with_authority,
+    },
+    Miner,
+};
+
+/// Base fee paid per transaction signature, in lamports.
+const LAMPORTS_PER_SIGNATURE: u64 = 5000;
+
+/// Compute units each instruction gets when a transaction sets no limit, and the most a
+/// transaction can get.
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u32 = 200_000;
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Number of difficulty levels above the minimum to consider, as in `ore rewards`.
+const DIFFICULTY_LEVELS: u32 = 32;
+
+/// Highest difficulty a hash can have.
+const MAX_DIFFICULTY: u32 = 128;
+
+/// Smallest probability worth printing in the difficulty table.
+const MIN_PRINTED_PROBABILITY: f64 = 0.0001;
+
+/// Number of rounds a miner can submit per hour.
+const ROUNDS_PER_HOUR: f64 = 3600.0 / ONE_MINUTE as f64;
+
+/// The fee of a single-signature transaction with the given priority fee and compute limit,
+/// in lamports.
+pub fn transaction_fee(priority_fee: u64, cu_limit: u32) -> u64 {
+    let priority_lamports = (priority_fee as u128 * cu_limit as u128).div_ceil(1_000_000) as u64;
+    LAMPORTS_PER_SIGNATURE + priority_lamports
+}
+
+/// The fee of a transaction, in lamports, from its signatures and compute budget
+/// instructions.
+pub fn message_fee(message: &VersionedMessage) -> u64 {
+    let signatures = message.header().num_required_signatures as u64;
+    LAMPORTS_PER_SIGNATURE * signatures + priority_fee_lamports(message)
+}
+
+/// The priority fee of a transaction, in lamports, from its compute budget instructions.
+pub fn priority_fee_lamports(message: &VersionedMessage) -> u64 {
+    let keys = message.static_account_keys();
+    let mut cu_limit = None;
+    let mut cu_price = 0;
+    let mut default_cu_limit = 0u32;
+    for ix in message.instructions() {
+        if keys.get(ix.program_id_index as usize) != Some(&compute_budget::id()) {
+            default_cu_limit =
+                default_cu_limit.saturating_add(DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT);
+            continue;
+        }
+        match try_from_slice_unchecked(&ix.data) {
+            Ok(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) => cu_limit = Some(limit),
+            Ok(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => cu_price = price,
+            _ => {}
+        }
+    }
+
+    // Without a limit, each instruction other than the compute budget ones gets the default
+    let cu_limit = cu_limit.unwrap_or(default_cu_limit.min(MAX_COMPUTE_UNIT_LIMIT));
+    transaction_fee(cu_price, cu_limit) - LAMPORTS_PER_SIGNATURE
+}
+
+/// The chance of a round's best hash landing on a difficulty, and what it pays.
+pub struct RewardLevel {
+    pub difficulty: u32,
+    pub reward: f64,
+    pub probability: f64,
+}
+
+impl RewardLevel {
+    pub fn expected(&self) -> f64 {
+        self.reward * self.probability
+    }
+}
+
+impl Miner {
+    pub async fn estimate(&self, args: EstimateArgs) {
+        // Measure hashrate, if not given
+        let hashrate = match args.hashrate {
+            Some(hashrate) => hashrate,
+            None => {
+                let threads = resolve_threads(args.threads.map_or(Threads::Auto, Threads::Count));
+                let progress_bar = spinner::new_progress_bar();
+                progress_bar.set_message(format!(
+                    "Measuring hashrate on {} threads. This will take {} sec...",
+                    threads, args.duration
+                ));
+                let result = run_benchmark(threads, args.duration, true);
+                progress_bar.finish_and_clear();
+                result.hashrate
+            }
+        };
+
+        // Fetch stake, if not given
+        let config = get_config(&self.rpc_client).await;
+        let stake = match args.stake {
+            Some(stake) => amount_f64_to_u64(stake),
+            None => try_get_proof_with_authority(&self.rpc_client, self.signer().pubkey())
+                .await
+                .map_or(0, |proof| proof.balance),
+        };
+        let multiplier = stake_multiplier(stake, config.top_balance);
+
+        // Expected reward of submitting the best hash found in each round
+        let levels = reward_levels(hashrate, &config, multiplier);
+        let ore_per_round: f64 = levels.iter().map(RewardLevel::expected).sum();
+        let ore_per_hour = ore_per_round * ROUNDS_PER_HOUR;
+
+        // Fees of submitting one transaction per round
+        let lamports_per_round = transaction_fee(self.priority_fee(), CU_LIMIT_SUBMIT);
+        let sol_per_hour = lamports_to_sol(lamports_per_round) * ROUNDS_PER_HOUR;
+
+        // Print results
+        println!("{}: {:.0} H/sec", "Hashrate".bold(), hashrate);
+        println!(
+            "{}: {} ORE ({:.2}x multiplier)",
+            "Stake".bold(),
+            amount_u64_to_f64(stake),
+            multiplier
+        );
+        println!("{}", "Best hash per round:".bold());
+        for level in levels {
+            if level.probability >= MIN_PRINTED_PROBABILITY {
+                println!(
+                    "  {:>2}: {:>6.2}% for {:.*} ORE",
+                    level.difficulty,
+                    level.probability * 100.0,
+                    TOKEN_DECIMALS as usize,
+                    level.reward
+                );
+            }
+        }
+        println!(
+            "{}: {:.*} ORE/hour",
+            "Expected rewards".bold(),
+            TOKEN_DECIMALS as usize,
+            ore_per_hour
+        );
+        println!(
+            "{}: {:.9} SOL/hour (priority fee: {} microlamports)",
+            "Transaction fees".bold(),
+            sol_per_hour,
+            self.priority_fee()
+        );
+        if ore_per_hour > 0.0 {
+            println!(
+                "{}: {:.9} SOL per ORE",
+                "Break-even price".bold(),
+                sol_per_hour / ore_per_hour
+            );
+        }
+        if let Some(ore_price) = args.ore_price {
+            // The most a round's transaction can cost before it loses money
+            let max_lamports = ore_per_round * ore_price * 1e9;
+            let max_priority_lamports = max_lamports - LAMPORTS_PER_SIGNATURE as f64;
+            if max_priority_lamports > 0.0 {
+                println!(
+                    "{}: {:.0} microlamports",
+                    "Break-even priority fee".bold(),
+                    max_priority_lamports * 1_000_000.0 / CU_LIMIT_SUBMIT as f64
+                );
+            } else {
+                println!(
+                    "{}: none, expected rewards do not cover the base fee",
+                    "Break-even priority fee".bold()
+                );
+            }
+        }
+    }
+}
+
+/// Reward levels for submitting the best hash found in each round at `hashrate`.
+pub fn reward_levels(hashrate: f64, config: &Config, multiplier: f64) -> Vec<RewardLevel> {
+    let hashes_per_round = hashrate * ONE_MINUTE as f64;
+    (0..DIFFICULTY_LEVELS)
+        .map(|i| {
+            let difficulty = config.min_difficulty as u32 + i;
+            RewardLevel {
+                difficulty,
+                reward: hash_reward(difficulty, config, multiplier),
+                probability: prob_best_at_least(hashes_per_round, difficulty)
+                    - prob_best_at_least(hashes_per_round, difficulty + 1),
+            }
+        })
+        .collect()
+}
+
+/// ORE paid for a hash of the given difficulty, or nothing below the minimum difficulty.
+pub fn hash_reward(difficulty: u32, config: &Config, multiplier: f64) -> f64 {
+    let Some(levels) = (difficulty as u64).checked_sub(config.min_difficulty) else {
+        return 0.0;
+    };
+    amount_u64_to_f64(config.base_reward_rate) * 2f64.powi(levels as i32) * multiplier
+}
+
+/// The lowest difficulty whose reward, at `ore_price` SOL per ORE, covers `fee` lamports.
+pub fn break_even_difficulty(fee: u64, ore_price: f64, config: &Config, multiplier: f64) -> u32 {
+    let mut difficulty = config.min_difficulty as u32;
+    while sol_to_lamports(hash_reward(difficulty, config, multiplier) * ore_price).lt(&fee)
+        && difficulty.lt(&MAX_DIFFICULTY)
+    {
+        difficulty += 1;
+    }
+    difficulty
+}
+
+/// Expected ORE mined per hour at `hashrate`.
+pub fn expected_ore_per_hour(hashrate: f64, config: &Config, multiplier: f64) -> f64 {
+    reward_levels(hashrate, config, multiplier)
+        .iter()
+        .map(RewardLevel::expected)
+        .sum::<f64>()
+        * ROUNDS_PER_HOUR
+}
+
+/// Probability that the best of `hashes` hashes has at least `difficulty` leading zeros.
+fn prob_best_at_least(hashes: f64, difficulty: u32) -> f64 {
+    // 1 - (1 - 2^-d)^n, computed without losing precision for small 2^-d
+    let p = 2f64.powi(-(difficulty as i32));
+    -(hashes * (-p).ln_1p()).exp_m1()
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{instruction::Instruction, message::Message, pubkey::Pubkey};
+
+    use super::*;
+
+    /// 0.01 ORE for a hash of difficulty 8.
+    fn config() -> Config {
+        Config {
+            base_reward_rate: 10u64.pow(TOKEN_DECIMALS as u32 - 2),
+            last_reset_at: 0,
+            min_difficulty: 8,
+            top_balance: 0,
+        }
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn prob_best_at_least_matches_closed_form() {
+        assert_eq!(prob_best_at_least(0.0, 8), 0.0);
+        assert_close(prob_best_at_least(1.0, 0), 1.0);
+        assert_close(prob_best_at_least(1.0, 1), 0.5);
+        assert_close(prob_best_at_least(2.0, 1), 0.75);
+
+        // 2^d hashes find a hash of difficulty d with probability close to 1 - 1/e
+        assert_close(prob_best_at_least(2f64.powi(30), 30), 1.0 - (-1f64).exp());
+    }
+
+    #[test]
+    fn hash_reward_doubles_per_difficulty() {
+        let config = config();
+        assert_eq!(hash_reward(7, &config, 1.0), 0.0);
+        assert_close(hash_reward(8, &config, 1.0), 0.01);
+        assert_close(hash_reward(10, &config, 1.0), 0.04);
+        assert_close(hash_reward(10, &config, 1.5), 0.06);
+    }
+
+    #[test]
+    fn break_even_difficulty_covers_the_fee() {
+        let config = config();
+        assert_eq!(break_even_difficulty(5000, 1.0, &config, 1.0), 8);
+        assert_eq!(break_even_difficulty(30_000_000, 1.0, &config, 1.0), 10);
+        assert_eq!(break_even_difficulty(30_000_000, 1.0, &config, 2.0), 9);
+        assert_eq!(
+            break_even_difficulty(5000, 0.0, &config, 1.0),
+            MAX_DIFFICULTY
+        );
+    }
+
+    #[test]
+    fn transaction_fee_rounds_the_priority_fee_up() {
+        assert_eq!(transaction_fee(0, 200_000), 5000);
+        assert_eq!(transaction_fee(1, 1), 5001);
+        assert_eq!(transaction_fee(1_000_000, 200_000), 205_000);
+        assert_eq!(transaction_fee(10_000, 150_000), 6500);
+    }
+
+    #[test]
+    fn message_fee_defaults_the_limit_per_instruction() {
+        let payer = Pubkey::new_unique();
+        let ix = || Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![]);
+        let message =
+            |ixs: &[Instruction]| VersionedMessage::Legacy(Message::new(ixs, Some(&payer)));
+
+        // Compute budget instructions don't get a default limit of their own
+        let price = ComputeBudgetInstruction::set_compute_unit_price(1_000_000);
+        assert_eq!(message_fee(&message(&[ix()])), 5000);
+        assert_eq!(message_fee(&message(&[price.clone(), ix(), ix()])), 405_000);
+
+        // The default limits add up to no more than the most a transaction can get
+        let ixs: Vec<_> = std::iter::once(price.clone())
+            .chain((0..8).map(|_| ix()))
+            .collect();
+        assert_eq!(message_fee(&message(&ixs)), 1_405_000);
+
+        // An explicit limit wins
+        let limit = ComputeBudgetInstruction::set_compute_unit_limit(10_000);
+        assert_eq!(message_fee(&message(&[limit, price, ix()])), 15_000);
+    }
+}
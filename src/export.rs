@@ -0,0 +1,422 @@
+use std::{collections::HashSet, fs, str::FromStr};
+
+use chrono::{Local, TimeZone};
+use futures::{stream, StreamExt};
+use ore_api::{
+    consts::{MINT_ADDRESS, TOKEN_DECIMALS, TOKEN_DECIMALS_V1},
+    instruction::OreInstruction,
+};
+use serde::Serialize;
+use solana_client::{
+    rpc_client::GetConfirmedSignaturesForAddress2Config, rpc_config::RpcTransactionConfig,
+};
+use solana_program::{instruction::CompiledInstruction, pubkey::Pubkey};
+use solana_rpc_client::spinner;
+use solana_sdk::{native_token::lamports_to_sol, signature::Signature, signer::Signer};
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding};
+use spl_associated_token_account::get_associated_token_address;
+use spl_token::amount_to_ui_amount;
+
+use crate::{
+    args::{ExportArgs, ExportFormat},
+    attribution::parse_mine_event,
+    estimate::priority_fee_lamports,
+    history::parse_date,
+    Miner,
+};
+
+/// Largest page of signatures the RPC returns.
+const SIGNATURES_PAGE_SIZE: usize = 1000;
+
+/// Most transactions fetched at once.
+const FETCH_CONCURRENCY: usize = 8;
+
+/// One transaction, classified for accounting.
+#[derive(Serialize, Debug)]
+pub struct ExportRow {
+    pub timestamp: i64,
+    pub date: String,
+    pub signature: String,
+
+    /// The ORE instructions in the transaction, joined by `+`, or `other`.
+    pub kind: String,
+
+    /// ORE claimed, staked, upgraded or mined.
+    pub amount: f64,
+
+    /// Total fee in SOL, if paid by the exported wallet.
+    pub fee: f64,
+
+    /// Portion of the fee paid as priority fee, in SOL.
+    pub priority_fee: f64,
+    pub success: bool,
+}
+
+impl Miner {
+    pub async fn export(&self, args: ExportArgs) {
+        let authority = match &args.wallet {
+            Some(wallet) => Pubkey::from_str(wallet).expect("Failed to parse wallet address"),
+            None => self.signer().pubkey(),
+        };
+        let from = args
+            .from
+            .as_deref()
+            .map(parse_date)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .and_then(|time| Local.from_local_datetime(&time).earliest())
+            .map(|time| time.timestamp());
+        let to = args
+            .to
+            .as_deref()
+            .map(parse_date)
+            .and_then(|date| date.and_hms_opt(23, 59, 59))
+            .and_then(|time| Local.from_local_datetime(&time).latest())
+            .map(|time| time.timestamp());
+
+        // Collect signatures for the wallet and its ORE token account
+        let progress_bar = spinner::new_progress_bar();
+        progress_bar.set_message("Fetching signatures...");
+        let token_account = get_associated_token_address(&authority, &MINT_ADDRESS);
+        let mut seen = HashSet::new();
+        let mut signatures = vec![];
+        for address in [authority, token_account] {
+            for (signature, block_time) in self.get_signatures(&address, from, to).await {
+                if seen.insert(signature) {
+                    signatures.push((signature, block_time));
+                }
+            }
+        }
+        signatures.sort_by_key(|(_, block_time)| *block_time);
+
+        // Classify each transaction
+        let mut rows = vec![];
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(self.rpc_client.commitment()),
+            max_supported_transaction_version: Some(0),
+        };
+        let mut fetches = stream::iter(signatures.iter())
+            .map(|(signature, _)| async move {
+                let tx = self
+                    .rpc_client
+                    .get_transaction_with_config(signature, config)
+                    .await;
+                (signature, tx)
+            })
+            .buffered(FETCH_CONCURRENCY);
+        let mut fetched = 0;
+        while let Some((signature, tx)) = fetches.next().await {
+            fetched += 1;
+            progress_bar.set_message(format!(
+                "Fetching transactions... ({} of {})",
+                fetched,
+                signatures.len()
+            ));
+            match tx {
+                Ok(tx) => rows.extend(classify(signature, &tx, &authority)),
+                Err(err) => progress_bar.println(format!(
+                    "Failed to fetch transaction {}: {}",
+                    signature, err
+                )),
+            }
+        }
+        progress_bar.finish_and_clear();
+
+        // Write rows
+        let output = match args.format {
+            ExportFormat::Csv => {
+                let mut csv =
+                    "timestamp,date,signature,kind,amount,fee,priority_fee,success\n".to_string();
+                for row in rows.iter() {
+                    csv.push_str(&format!(
+                        "{},{},{},{},{},{:.9},{:.9},{}\n",
+                        row.timestamp,
+                        row.date,
+                        row.signature,
+                        row.kind,
+                        row.amount,
+                        row.fee,
+                        row.priority_fee,
+                        row.success
+                    ));
+                }
+                csv
+            }
+            ExportFormat::Json => {
+                serde_json::to_string_pretty(&rows).expect("Failed to serialize transactions")
+            }
+        };
+        match &args.output {
+            Some(path) => {
+                fs::write(path, output).expect("Failed to write export");
+                println!("Exported {} transactions to {}", rows.len(), path);
+            }
+            None => print!("{}", output),
+        }
+    }
+
+    /// Pages through an address's signatures, newest first, within the time range.
+    async fn get_signatures(
+        &self,
+        address: &Pubkey,
+        from: Option<i64>,
+        to: Option<i64>,
+    ) -> Vec<(Signature, i64)> {
+        let mut signatures = vec![];
+        let mut before = None;
+        loop {
+            let page = self
+                .rpc_client
+                .get_signatures_for_address_with_config(
+                    address,
+                    GetConfirmedSignaturesForAddress2Config {
+                        before,
+                        until: None,
+                        limit: Some(SIGNATURES_PAGE_SIZE),
+                        commitment: Some(self.rpc_client.commitment()),
+                    },
+                )
+                .await
+                .expect("Failed to fetch signatures");
+            let Some(last) = page.last() else {
+                break;
+            };
+            before = Signature::from_str(&last.signature).ok();
+            let done = page.len() < SIGNATURES_PAGE_SIZE
+                || last
+                    .block_time
+                    .is_some_and(|block_time| from.is_some_and(|from| block_time < from));
+            for status in page {
+                let Some(block_time) = status.block_time else {
+                    continue;
+                };
+                if from.is_some_and(|from| block_time < from)
+                    || to.is_some_and(|to| block_time > to)
+                {
+                    continue;
+                }
+                if let Ok(signature) = Signature::from_str(&status.signature) {
+                    signatures.push((signature, block_time));
+                }
+            }
+            if done || before.is_none() {
+                break;
+            }
+        }
+        signatures
+    }
+}
+
+/// Summarizes a transaction's ORE instructions, amounts and fees.
+fn classify(
+    signature: &Signature,
+    tx: &EncodedConfirmedTransactionWithStatusMeta,
+    authority: &Pubkey,
+) -> Option<ExportRow> {
+    let meta = tx.transaction.meta.as_ref()?;
+    let message = tx.transaction.transaction.decode()?.message;
+    let keys = message.static_account_keys();
+    let program_id = |ix: &CompiledInstruction| keys.get(ix.program_id_index as usize).copied();
+
+    // Classify ORE instructions
+    let mut kinds: Vec<&str> = vec![];
+    let mut amount = 0.0;
+    for ix in message.instructions() {
+        if program_id(ix) != Some(ore_api::ID) {
+            continue;
+        }
+        let Some(instruction) = ix
+            .data
+            .first()
+            .and_then(|tag| OreInstruction::try_from(*tag).ok())
+        else {
+            continue;
+        };
+        let ix_amount = read_u64(&ix.data, 8).unwrap_or(0);
+        let kind = match instruction {
+            OreInstruction::Claim => {
+                amount += amount_to_ui_amount(ix_amount, TOKEN_DECIMALS);
+                "claim"
+            }
+            OreInstruction::Close => "close",
+            OreInstruction::Mine => "mine",
+            OreInstruction::Open => "open",
+            OreInstruction::Reset => "reset",
+            OreInstruction::Stake => {
+                amount += amount_to_ui_amount(ix_amount, TOKEN_DECIMALS);
+                "stake"
+            }
+            OreInstruction::Update => "update",
+            OreInstruction::Upgrade => {
+                amount += amount_to_ui_amount(ix_amount, TOKEN_DECIMALS_V1);
+                "upgrade"
+            }
+            OreInstruction::Initialize => "initialize",
+        };
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+
+    // Mine rewards are only known from the program's return data
+    if kinds.contains(&"mine") {
+        if let Some(event) = parse_mine_event(tx) {
+            amount += amount_to_ui_amount(event.reward, TOKEN_DECIMALS);
+        }
+    }
+
+    // A failed transaction moved no ORE, though it still paid its fee
+    let success = meta.err.is_none();
+    if !success {
+        amount = 0.0;
+    }
+
+    // Fees are only a cost to the wallet if it paid them
+    let paid = keys.first() == Some(authority);
+    let priority_lamports = priority_fee_lamports(&message);
+    let timestamp = tx.block_time.unwrap_or(0);
+    Some(ExportRow {
+        timestamp,
+        date: Local
+            .timestamp_opt(timestamp, 0)
+            .single()
+            .map_or(String::new(), |time| time.to_rfc3339()),
+        signature: signature.to_string(),
+        kind: if kinds.is_empty() {
+            "other".to_string()
+        } else {
+            kinds.join("+")
+        },
+        amount,
+        fee: if paid { lamports_to_sol(meta.fee) } else { 0.0 },
+        priority_fee: if paid {
+            lamports_to_sol(priority_lamports.min(meta.fee))
+        } else {
+            0.0
+        },
+        success,
+    })
+}
+
+/// Reads a little-endian integer of `len` bytes following an instruction's tag byte.
+fn read_u64(data: &[u8], len: usize) -> Option<u64> {
+    let mut bytes = [0; 8];
+    bytes[..len].copy_from_slice(data.get(1..1 + len)?);
+    Some(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use solana_sdk::{
+        compute_budget::ComputeBudgetInstruction,
+        instruction::{AccountMeta, Instruction, InstructionError},
+        message::{Message, VersionedMessage},
+        transaction::{TransactionError, VersionedTransaction},
+    };
+    use solana_transaction_status::{
+        EncodedTransaction, EncodedTransactionWithStatusMeta, TransactionBinaryEncoding,
+        TransactionStatusMeta,
+    };
+
+    use super::*;
+
+    fn confirmed(
+        ixs: &[Instruction],
+        payer: &Pubkey,
+        status: Result<(), TransactionError>,
+    ) -> EncodedConfirmedTransactionWithStatusMeta {
+        let message = Message::new(ixs, Some(payer));
+        let tx = VersionedTransaction {
+            signatures: vec![Signature::default(); message.header.num_required_signatures as usize],
+            message: VersionedMessage::Legacy(message),
+        };
+        let data =
+            base64::engine::general_purpose::STANDARD.encode(bincode::serialize(&tx).unwrap());
+        EncodedConfirmedTransactionWithStatusMeta {
+            slot: 1,
+            transaction: EncodedTransactionWithStatusMeta {
+                transaction: EncodedTransaction::Binary(data, TransactionBinaryEncoding::Base64),
+                meta: Some(
+                    TransactionStatusMeta {
+                        status,
+                        fee: 15_000,
+                        ..TransactionStatusMeta::default()
+                    }
+                    .into(),
+                ),
+                version: None,
+            },
+            block_time: Some(1_700_000_000),
+        }
+    }
+
+    fn claim_ixs(authority: Pubkey) -> Vec<Instruction> {
+        vec![
+            ComputeBudgetInstruction::set_compute_unit_limit(10_000),
+            ComputeBudgetInstruction::set_compute_unit_price(1_000_000),
+            ore_api::instruction::claim(authority, authority, 10u64.pow(TOKEN_DECIMALS as u32)),
+        ]
+    }
+
+    #[test]
+    fn classify_claim() {
+        let authority = Pubkey::new_unique();
+        let tx = confirmed(&claim_ixs(authority), &authority, Ok(()));
+        let row = classify(&Signature::default(), &tx, &authority).unwrap();
+        assert_eq!(row.kind, "claim");
+        assert_eq!(row.amount, 1.0);
+        assert_eq!(row.fee, lamports_to_sol(15_000));
+        assert_eq!(row.priority_fee, lamports_to_sol(10_000));
+        assert_eq!(row.timestamp, 1_700_000_000);
+        assert!(row.success);
+    }
+
+    #[test]
+    fn classify_failed_transaction_moves_nothing() {
+        let authority = Pubkey::new_unique();
+        let status = Err(TransactionError::InstructionError(
+            2,
+            InstructionError::Custom(0),
+        ));
+        let tx = confirmed(&claim_ixs(authority), &authority, status);
+        let row = classify(&Signature::default(), &tx, &authority).unwrap();
+        assert_eq!(row.amount, 0.0);
+        assert_eq!(row.fee, lamports_to_sol(15_000));
+        assert!(!row.success);
+    }
+
+    #[test]
+    fn classify_fee_paid_by_someone_else() {
+        let authority = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let tx = confirmed(&claim_ixs(authority), &payer, Ok(()));
+        let row = classify(&Signature::default(), &tx, &authority).unwrap();
+        assert_eq!(row.amount, 1.0);
+        assert_eq!(row.fee, 0.0);
+        assert_eq!(row.priority_fee, 0.0);
+    }
+
+    #[test]
+    fn classify_other_transaction() {
+        let authority = Pubkey::new_unique();
+        let ixs = [Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            b"memo",
+            vec![AccountMeta::new(authority, true)],
+        )];
+        let tx = confirmed(&ixs, &authority, Ok(()));
+        let row = classify(&Signature::default(), &tx, &authority).unwrap();
+        assert_eq!(row.kind, "other");
+        assert_eq!(row.amount, 0.0);
+    }
+
+    #[test]
+    fn read_u64_after_tag() {
+        assert_eq!(read_u64(&[2, 0x10, 0x27, 0, 0], 4), Some(10_000));
+        assert_eq!(read_u64(&[3, 1, 0, 0, 0, 0, 0, 0, 0], 8), Some(1));
+        assert_eq!(read_u64(&[3, 1, 0, 0, 0, 0, 0, 0, 0, 9], 8), Some(1));
+        assert_eq!(read_u64(&[3, 1, 0, 0], 8), None);
+        assert_eq!(read_u64(&[], 4), None);
+    }
+}
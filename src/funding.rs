@@ -0,0 +1,218 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use chrono::Local;
+use colored::*;
+use solana_program::{
+    native_token::{lamports_to_sol, sol_to_lamports},
+    pubkey::Pubkey,
+    system_instruction,
+};
+use solana_sdk::{
+    signature::{Keypair, Signature},
+    signer::Signer,
+    transaction::Transaction,
+};
+
+use crate::{
+    args::MineArgs,
+    history::{local_date, read_records, topups_path, TopUpRecord},
+    signer::read_signer,
+    tui::{exit_with_error, print_line},
+    Miner,
+};
+
+/// How often to check the payer balance while waiting for funds.
+const FUNDS_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Delay between attempts to fetch the payer balance after a top-up.
+const BALANCE_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// How `ore mine` keeps the payer funded.
+pub struct Funding {
+    /// Wait for funds instead of exiting when the payer balance is too low.
+    wait: bool,
+    top_up: Option<TopUp>,
+}
+
+/// Transfers from a funding keypair to the payer, up to a daily cap.
+struct TopUp {
+    keypair: Keypair,
+
+    /// Lamports sent per top-up.
+    amount: u64,
+
+    /// Most lamports sent per day, in local time.
+    daily_cap: u64,
+
+    /// Set once a top-up could not be recorded, as the daily cap can't be enforced without
+    /// the history.
+    unrecorded: AtomicBool,
+}
+
+impl Funding {
+    /// Reads the funding settings from the mining args, or `None` if none are set.
+    pub fn from_args(args: &MineArgs, password_file: &Option<String>) -> Option<Self> {
+        if !args.wait_for_funds && args.top_up_keypair.is_none() {
+            return None;
+        }
+        Some(Self {
+            wait: args.wait_for_funds,
+            top_up: args.top_up_keypair.as_ref().map(|source| TopUp {
+                keypair: read_signer(source, password_file, None),
+                amount: sol_to_lamports(args.top_up_amount),
+                daily_cap: sol_to_lamports(args.top_up_daily_cap),
+                unrecorded: AtomicBool::new(false),
+            }),
+        })
+    }
+
+    /// The funding keypair's address, if top-ups are on.
+    pub fn funder(&self) -> Option<Pubkey> {
+        self.top_up.as_ref().map(|top_up| top_up.keypair.pubkey())
+    }
+}
+
+impl Miner {
+    /// Makes sure the payer holds more than the minimum balance before a transaction.
+    ///
+    /// Tops the payer up from the funding keypair, if one is set, and then waits for funds
+    /// if `--wait-for-funds` is set. Otherwise prints an error and exits, as the transaction
+    /// would fail anyway.
+    pub async fn ensure_funded(&self, payer: Pubkey, mut balance: u64) {
+        let min_balance = sol_to_lamports(self.min_sol_balance);
+        let funding = self.funding.get();
+        let mut waiting = false;
+        while balance <= min_balance {
+            if let Some(top_up) = funding.and_then(|funding| funding.top_up.as_ref()) {
+                match self.top_up(top_up, payer).await {
+                    Ok(signature) => {
+                        print_line(format!(
+                            "Topped up {} SOL from {} ({})",
+                            lamports_to_sol(top_up.amount),
+                            top_up.keypair.pubkey(),
+                            signature
+                        ));
+                        balance = self.refresh_balance(payer).await;
+                        continue;
+                    }
+                    // Only explain once, rather than on every poll
+                    Err(err) if !waiting => print_line(format!(
+                        "{} Failed to top up: {}",
+                        "WARNING".bold().yellow(),
+                        err
+                    )),
+                    Err(_) => {}
+                }
+            }
+            if !funding.is_some_and(|funding| funding.wait) {
+                exit_with_error(format!(
+                    "Insufficient balance: {} SOL\nPlease top up with at least {} SOL",
+                    lamports_to_sol(balance),
+                    self.min_sol_balance
+                ));
+            }
+            if !waiting {
+                print_line(format!(
+                    "{} Insufficient balance: {} SOL. Waiting for more than {} SOL in {}...",
+                    "WARNING".bold().yellow(),
+                    lamports_to_sol(balance),
+                    self.min_sol_balance,
+                    payer
+                ));
+                waiting = true;
+            }
+            tokio::time::sleep(FUNDS_POLL_INTERVAL).await;
+            if let Ok(latest) = self.rpc_client.get_balance(&payer).await {
+                balance = latest;
+            }
+        }
+        if waiting {
+            print_line(format!(
+                "Payer funded with {} SOL",
+                lamports_to_sol(balance)
+            ));
+        }
+    }
+
+    /// Fetches the payer balance after a top-up, retrying until it succeeds so the stale
+    /// balance never triggers another transfer.
+    async fn refresh_balance(&self, payer: Pubkey) -> u64 {
+        let mut warned = false;
+        loop {
+            match self.rpc_client.get_balance(&payer).await {
+                Ok(balance) => return balance,
+                Err(err) if !warned => {
+                    print_line(format!(
+                        "{} Failed to fetch balance after top-up, retrying: {}",
+                        "WARNING".bold().yellow(),
+                        err
+                    ));
+                    warned = true;
+                }
+                Err(_) => {}
+            }
+            tokio::time::sleep(BALANCE_RETRY_DELAY).await;
+        }
+    }
+
+    /// Sends a top-up to the payer, unless it would go over the daily cap.
+    async fn top_up(&self, top_up: &TopUp, payer: Pubkey) -> Result<Signature, String> {
+        if top_up.unrecorded.load(Ordering::Relaxed) {
+            return Err(format!(
+                "daily cap reached, as a top-up could not be recorded in {}",
+                topups_path().display()
+            ));
+        }
+        let funder = top_up.keypair.pubkey();
+        let today = Local::now().date_naive();
+        let spent: u64 = read_records::<TopUpRecord>(&topups_path())
+            .iter()
+            .filter(|record| {
+                record.funder.eq(&funder.to_string()) && local_date(record.timestamp).eq(&today)
+            })
+            .map(|record| record.amount)
+            .sum();
+        if spent.saturating_add(top_up.amount) > top_up.daily_cap {
+            return Err(format!(
+                "daily cap of {} SOL reached ({} SOL sent today)",
+                lamports_to_sol(top_up.daily_cap),
+                lamports_to_sol(spent)
+            ));
+        }
+
+        // The funding keypair pays for its own transfer
+        let ix = system_instruction::transfer(&funder, &payer, top_up.amount);
+        let (hash, _slot) = self
+            .rpc_client
+            .get_latest_blockhash_with_commitment(self.rpc_client.commitment())
+            .await
+            .map_err(|err| err.to_string())?;
+        let tx = Transaction::new_signed_with_payer(&[ix], Some(&funder), &[&top_up.keypair], hash);
+        let signature = self
+            .rpc_client
+            .send_and_confirm_transaction(&tx)
+            .await
+            .map_err(|err| err.to_string())?;
+        self.record_fee(&tx.into());
+        let record = TopUpRecord {
+            timestamp: chrono::Utc::now().timestamp(),
+            funder: funder.to_string(),
+            payer: payer.to_string(),
+            amount: top_up.amount,
+            signature: signature.to_string(),
+        };
+        if let Err(err) = record.save() {
+            top_up.unrecorded.store(true, Ordering::Relaxed);
+            print_line(format!(
+                "{} Failed to record top-up in {}, so no more top-ups will be sent: {}",
+                "WARNING".bold().yellow(),
+                topups_path().display(),
+                err
+            ));
+        }
+        Ok(signature)
+    }
+}
@@ -0,0 +1,379 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    args::{HistoryArgs, HistoryGroup},
+    utils::{amount_u64_to_string, ore_dir},
+    Miner,
+};
+
+/// Bytes read at a time when reading a history file backwards.
+const READ_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// A claim sent by this CLI, as recorded in the claim history.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ClaimRecord {
+    /// Unix timestamp the claim was sent at.
+    pub timestamp: i64,
+    pub authority: String,
+    pub beneficiary: String,
+    pub amount: u64,
+    pub signature: String,
+
+    /// What triggered the claim: `manual`, `batch`, `threshold`, `schedule`,
+    /// `control` or `api`.
+    pub trigger: String,
+}
+
+impl ClaimRecord {
+    pub fn save(&self) -> std::io::Result<()> {
+        append(&claims_path(), self)
+    }
+}
+
+/// A mining round, as recorded in the round history.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RoundRecord {
+    /// Unix timestamp the solution was submitted at.
+    pub timestamp: i64,
+    pub authority: String,
+    pub challenge: String,
+    pub difficulty: u32,
+    pub nonce: u64,
+
+    /// Hashes computed during the round, across all threads.
+    pub hashes: u64,
+
+    /// Seconds until the cutoff when the round started.
+    pub cutoff: u64,
+    pub bus: usize,
+    pub priority_fee: u64,
+    pub signature: Option<String>,
+    pub landed: bool,
+    pub error: Option<String>,
+
+    /// Reward credited for the hash, as reported by the program, or the increase in the
+    /// proof balance if the transaction could not be read.
+    pub reward: Option<u64>,
+
+    /// Seconds between the hash landing and its deadline, as reported by the program.
+    #[serde(default)]
+    pub timing: Option<i64>,
+
+    /// Whether `reward` is the increase in the proof balance rather than what the program
+    /// reported.
+    #[serde(default)]
+    pub estimated: bool,
+}
+
+impl RoundRecord {
+    pub fn save(&self) -> std::io::Result<()> {
+        append(&rounds_path(), self)
+    }
+
+    /// The reward for display, marked with `~` if it is estimated.
+    pub fn reward_string(&self) -> String {
+        match self.reward {
+            Some(reward) if self.estimated => format!("~{}", amount_u64_to_string(reward)),
+            Some(reward) => amount_u64_to_string(reward),
+            None => "-".to_string(),
+        }
+    }
+}
+
+/// A transfer from the funding keypair to the payer, as recorded in the top-up history.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TopUpRecord {
+    /// Unix timestamp the top-up was sent at.
+    pub timestamp: i64,
+    pub funder: String,
+    pub payer: String,
+
+    /// Amount transferred, in lamports.
+    pub amount: u64,
+    pub signature: String,
+}
+
+impl TopUpRecord {
+    pub fn save(&self) -> std::io::Result<()> {
+        append(&topups_path(), self)
+    }
+}
+
+/// The fee of a transaction sent by this CLI which landed, as recorded in the fee history.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FeeRecord {
+    /// Unix timestamp the transaction landed at.
+    pub timestamp: i64,
+    pub payer: String,
+    pub signature: String,
+
+    /// Fee paid, in lamports, whether or not the transaction succeeded.
+    pub fee: u64,
+}
+
+impl FeeRecord {
+    pub fn save(&self) -> std::io::Result<()> {
+        append(&fees_path(), self)
+    }
+}
+
+/// Totals over a group of rounds.
+#[derive(Default)]
+struct RoundSummary {
+    rounds: u64,
+    landed: u64,
+    hashes: u64,
+    difficulty_sum: u64,
+    max_difficulty: u32,
+    rewards: u64,
+}
+
+impl RoundSummary {
+    fn add(&mut self, round: &RoundRecord) {
+        self.rounds += 1;
+        self.landed += round.landed as u64;
+        self.hashes += round.hashes;
+        self.difficulty_sum += round.difficulty as u64;
+        self.max_difficulty = self.max_difficulty.max(round.difficulty);
+        self.rewards += round.reward.unwrap_or(0);
+    }
+}
+
+impl Miner {
+    pub async fn history(&self, args: HistoryArgs) {
+        let from = args.from.as_deref().map(parse_date);
+        let to = args.to.as_deref().map(parse_date);
+        let rounds: Vec<RoundRecord> = read_records(&rounds_path())
+            .into_iter()
+            .filter(|round: &RoundRecord| {
+                let day = local_date(round.timestamp);
+                from.iter().all(|from| day.ge(from))
+                    && to.iter().all(|to| day.le(to))
+                    && args.wallet.iter().all(|wallet| round.authority.eq(wallet))
+            })
+            .collect();
+        if rounds.is_empty() {
+            println!("No rounds recorded in {}", rounds_path().display());
+            return;
+        }
+
+        // List individual rounds
+        if args.rounds {
+            println!(
+                "{:<19}  {:<44}  {:>10}  {:>6}  {:>12}  Signature",
+                "Time", "Wallet", "Difficulty", "Landed", "Reward (ORE)"
+            );
+            for round in rounds.iter() {
+                println!(
+                    "{:<19}  {:<44}  {:>10}  {:>6}  {:>12}  {}",
+                    local_time(round.timestamp).format("%Y-%m-%d %H:%M:%S"),
+                    round.authority,
+                    round.difficulty,
+                    if round.landed { "yes" } else { "no" },
+                    round.reward_string(),
+                    round
+                        .signature
+                        .as_deref()
+                        .or(round.error.as_deref())
+                        .unwrap_or("-")
+                );
+            }
+            return;
+        }
+
+        // Summarize by day or wallet
+        let mut groups: BTreeMap<String, RoundSummary> = BTreeMap::new();
+        let mut total = RoundSummary::default();
+        for round in rounds.iter() {
+            total.add(round);
+            let key = match args.group_by {
+                HistoryGroup::Day => local_date(round.timestamp).to_string(),
+                HistoryGroup::Wallet => round.authority.clone(),
+            };
+            groups.entry(key).or_default().add(round);
+        }
+        println!(
+            "{:<44}  {:>6}  {:>6}  {:>14}  {:>8}  {:>8}  {:>14}",
+            match args.group_by {
+                HistoryGroup::Day => "Day",
+                HistoryGroup::Wallet => "Wallet",
+            },
+            "Rounds",
+            "Landed",
+            "Hashes",
+            "Avg diff",
+            "Max diff",
+            "Rewards (ORE)"
+        );
+        for (key, summary) in groups.iter() {
+            print_summary(key, summary);
+        }
+        print_summary("Total", &total);
+    }
+}
+
+fn print_summary(key: &str, summary: &RoundSummary) {
+    println!(
+        "{:<44}  {:>6}  {:>6}  {:>14}  {:>8.1}  {:>8}  {:>14}",
+        key,
+        summary.rounds,
+        summary.landed,
+        summary.hashes,
+        summary.difficulty_sum as f64 / summary.rounds.max(1) as f64,
+        summary.max_difficulty,
+        amount_u64_to_string(summary.rewards)
+    );
+}
+
+pub fn parse_date(date: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap_or_else(|_| {
+        eprintln!("error: Invalid date `{}`, expected YYYY-MM-DD", date);
+        std::process::exit(1);
+    })
+}
+
+pub fn local_time(timestamp: i64) -> DateTime<Local> {
+    Local
+        .timestamp_opt(timestamp, 0)
+        .single()
+        .unwrap_or_default()
+}
+
+pub fn local_date(timestamp: i64) -> NaiveDate {
+    local_time(timestamp).date_naive()
+}
+
+pub fn history_dir() -> PathBuf {
+    ore_dir().join("history")
+}
+
+pub fn claims_path() -> PathBuf {
+    history_dir().join("claims.jsonl")
+}
+
+pub fn rounds_path() -> PathBuf {
+    history_dir().join("rounds.jsonl")
+}
+
+pub fn topups_path() -> PathBuf {
+    history_dir().join("topups.jsonl")
+}
+
+pub fn fees_path() -> PathBuf {
+    history_dir().join("fees.jsonl")
+}
+
+/// Reads every record in a history file, skipping lines which fail to parse.
+pub fn read_records<T: DeserializeOwned>(path: &Path) -> Vec<T> {
+    let Ok(file) = File::open(path) else {
+        return vec![];
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect()
+}
+
+/// Reads the last `limit` records in a history file which match `filter`, oldest first.
+///
+/// Reads the file backwards in chunks, so the cost depends on `limit` rather than on the
+/// size of the history.
+pub fn read_last_records<T: DeserializeOwned>(
+    path: &Path,
+    limit: usize,
+    filter: impl Fn(&T) -> bool,
+) -> Vec<T> {
+    let Ok(mut file) = File::open(path) else {
+        return vec![];
+    };
+    let Ok(mut pos) = file.seek(SeekFrom::End(0)) else {
+        return vec![];
+    };
+    let mut records = vec![];
+
+    // The start of the earliest line read so far, which may continue in the previous chunk
+    let mut partial = vec![];
+    while pos > 0 && records.len() < limit {
+        let size = READ_CHUNK_SIZE.min(pos);
+        pos -= size;
+        let mut chunk = vec![0; size as usize];
+        if file.seek(SeekFrom::Start(pos)).is_err() || file.read_exact(&mut chunk).is_err() {
+            break;
+        }
+        chunk.append(&mut partial);
+        let mut lines = chunk.split(|byte| byte.eq(&b'\n'));
+        if pos > 0 {
+            partial = lines.next().unwrap_or_default().to_vec();
+        }
+        for line in lines.rev() {
+            if records.len() == limit {
+                break;
+            }
+            if let Ok(record) = serde_json::from_slice::<T>(line) {
+                if filter(&record) {
+                    records.push(record);
+                }
+            }
+        }
+    }
+    records.reverse();
+    records
+}
+
+/// Appends a record as one JSON line.
+fn append<T: Serialize>(path: &Path, record: &T) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(record)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_last_records_across_chunks() {
+        let path = std::env::temp_dir().join(format!("ore-history-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        // Long enough lines that records straddle chunk boundaries
+        let padding = "x".repeat(1000);
+        for i in 0..300u64 {
+            append(&path, &serde_json::json!({ "i": i, "padding": padding })).unwrap();
+        }
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+
+        let index = |records: Vec<serde_json::Value>| -> Vec<u64> {
+            records
+                .iter()
+                .map(|record| record["i"].as_u64().unwrap())
+                .collect()
+        };
+        let all = |_: &serde_json::Value| true;
+        let early = |record: &serde_json::Value| record["i"].as_u64().unwrap() < 100;
+        assert_eq!(index(read_last_records(&path, 3, all)), vec![297, 298, 299]);
+        assert_eq!(index(read_last_records(&path, 2, early)), vec![98, 99]);
+        assert_eq!(
+            index(read_last_records(&path, 1000, all)),
+            (0..300).collect::<Vec<_>>()
+        );
+        assert_eq!(index(read_last_records(&path, 0, all)), Vec::<u64>::new());
+        fs::remove_file(&path).unwrap();
+    }
+}
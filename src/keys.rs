@@ -0,0 +1,100 @@
+use std::{fs, path::PathBuf};
+
+use colored::*;
+use solana_sdk::signature::{read_keypair_file, write_keypair_file, Keypair, Signer};
+
+use crate::{
+    args::{KeysArgs, KeysCommand},
+    keystore::{keystore_dir, read_new_password, read_password, Keystore},
+    signer::derive_signers,
+    utils::ask_confirm,
+    Miner,
+};
+
+impl Miner {
+    pub async fn keys(&self, args: KeysArgs) {
+        match args.command {
+            KeysCommand::New(args) => {
+                let keypair = Keypair::new();
+                self.write_keystore(&keypair, args.outfile);
+            }
+            KeysCommand::Import(args) => {
+                let keypair = read_keypair_file(&args.keypair)
+                    .unwrap_or_else(|_| panic!("No keypair found at {}", args.keypair));
+                self.write_keystore(&keypair, args.outfile);
+            }
+            KeysCommand::Export(args) => {
+                let Some(keystore) = Keystore::read(&args.keystore) else {
+                    println!("Not a keystore: {}", args.keystore);
+                    return;
+                };
+                if !self.yes
+                    && !ask_confirm(
+                        format!(
+                            "{} This will write the private key for {} to {} unencrypted.\nAre you sure you want to continue?",
+                            "WARNING".bold().yellow(),
+                            keystore.pubkey,
+                            args.outfile
+                        )
+                        .as_str(),
+                        false,
+                    )
+                {
+                    return;
+                }
+                let password = read_password(&self.password_file, &keystore.pubkey);
+                match keystore.decrypt(&password) {
+                    Ok(keypair) => {
+                        write_keypair_file(&keypair, &args.outfile)
+                            .expect("Failed to write keypair file");
+                        println!("Wrote {} to {}", keypair.pubkey(), args.outfile);
+                    }
+                    Err(err) => println!("{} {}", "ERROR".bold().red(), err),
+                }
+            }
+            KeysCommand::Derive(args) => {
+                let Some((start, end)) = args
+                    .range
+                    .split_once("..")
+                    .and_then(|(start, end)| Some((start.parse().ok()?, end.parse().ok()?)))
+                else {
+                    println!("Invalid range: {}", args.range);
+                    return;
+                };
+                let source = self.keypair_filepath.clone().expect("No keypair provided");
+                for (i, keypair) in derive_signers(&source, start..end).iter().enumerate() {
+                    println!("{} {}", start as usize + i, keypair.pubkey());
+                }
+                println!("\nMine with one of these wallets by passing --account-index <INDEX>, or with all of them by passing --account-index START..END.");
+            }
+            KeysCommand::List(_) => {
+                let dir = keystore_dir();
+                let Ok(entries) = fs::read_dir(&dir) else {
+                    println!("No keystores found in {}", dir.display());
+                    return;
+                };
+                for entry in entries.flatten() {
+                    if let Some(keystore) = Keystore::read(entry.path()) {
+                        println!("{} {}", keystore.pubkey, entry.path().display());
+                    }
+                }
+            }
+        }
+    }
+
+    fn write_keystore(&self, keypair: &Keypair, outfile: Option<String>) {
+        let path = match outfile {
+            Some(outfile) => PathBuf::from(outfile),
+            None => keystore_dir().join(format!("{}.json", keypair.pubkey())),
+        };
+        if path.exists() {
+            println!("{} already exists", path.display());
+            return;
+        }
+        let password = read_new_password(&self.password_file);
+        Keystore::encrypt(keypair, &password)
+            .write(&path)
+            .expect("Failed to write keystore");
+        println!("Wrote {} to {}", keypair.pubkey(), path.display());
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    fs,
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+};
+
+use aes_gcm_siv::{
+    aead::{Aead, NewAead},
+    Aes256GcmSiv, Key, Nonce,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::{Keypair, Signer};
+
+use crate::utils::ore_dir;
+
+const KEYSTORE_VERSION: u8 = 1;
+const KDF: &str = "argon2id";
+const KDF_ROUNDS: u32 = 3;
+const KDF_MEMORY_KIB: u32 = 64 * 1024;
+const CIPHER: &str = "aes-256-gcm-siv";
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// Environment variable read for the keystore password in unattended runs.
+pub const PASSWORD_ENV: &str = "ORE_KEYSTORE_PASSWORD";
+
+/// A password-encrypted keypair, stored as JSON on disk.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Keystore {
+    pub version: u8,
+    pub pubkey: String,
+    pub kdf: String,
+
+    /// Iterations of the KDF.
+    pub rounds: u32,
+
+    /// Memory cost of the KDF, in KiB.
+    pub memory: u32,
+    pub salt: String,
+    pub cipher: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl Keystore {
+    pub fn encrypt(keypair: &Keypair, password: &str) -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut salt);
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let key = derive_key(password, &salt, KDF_ROUNDS, KDF_MEMORY_KIB)
+            .expect("Failed to derive keystore key");
+        let cipher = Aes256GcmSiv::new(Key::from_slice(&key));
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), keypair.to_bytes().as_ref())
+            .expect("Failed to encrypt keypair");
+        Self {
+            version: KEYSTORE_VERSION,
+            pubkey: keypair.pubkey().to_string(),
+            kdf: KDF.to_string(),
+            rounds: KDF_ROUNDS,
+            memory: KDF_MEMORY_KIB,
+            salt: bs58::encode(salt).into_string(),
+            cipher: CIPHER.to_string(),
+            nonce: bs58::encode(nonce).into_string(),
+            ciphertext: bs58::encode(ciphertext).into_string(),
+        }
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<Keypair, String> {
+        if self.version != KEYSTORE_VERSION || self.kdf != KDF || self.cipher != CIPHER {
+            return Err(format!(
+                "Unsupported keystore (version {}, {}, {})",
+                self.version, self.kdf, self.cipher
+            ));
+        }
+        let decode = |s: &str| {
+            bs58::decode(s)
+                .into_vec()
+                .map_err(|_| "Malformed keystore".to_string())
+        };
+        let salt = decode(&self.salt)?;
+        let nonce = decode(&self.nonce)?;
+        let ciphertext = decode(&self.ciphertext)?;
+        if nonce.len() != NONCE_LEN {
+            return Err("Malformed keystore".to_string());
+        }
+        let key = derive_key(password, &salt, self.rounds, self.memory)?;
+        let cipher = Aes256GcmSiv::new(Key::from_slice(&key));
+        let bytes = cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| "Wrong password".to_string())?;
+        Keypair::from_bytes(&bytes).map_err(|err| err.to_string())
+    }
+
+    /// Reads a keystore from disk. Returns `None` if the file is not a keystore,
+    /// e.g. a plaintext keypair file.
+    pub fn read<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let data = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    /// Writes the keystore, creating the file readable only by its owner.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?
+            .write_all(serde_json::to_string_pretty(self)?.as_bytes())
+    }
+}
+
+/// Reads the keystore password from a file, the environment, or the TTY, in that order.
+pub fn read_password(password_file: &Option<String>, pubkey: &str) -> String {
+    if let Some(password_file) = password_file {
+        let password = fs::read_to_string(password_file)
+            .unwrap_or_else(|_| panic!("Failed to read password file {}", password_file));
+        return password.trim_end_matches(['\r', '\n']).to_string();
+    }
+    if let Ok(password) = std::env::var(PASSWORD_ENV) {
+        return password;
+    }
+    rpassword::prompt_password(format!("Password for {}: ", pubkey))
+        .expect("Failed to read password")
+}
+
+/// Prompts for a new password twice, unless one is supplied by file or environment.
+pub fn read_new_password(password_file: &Option<String>) -> String {
+    if password_file.is_some() || std::env::var(PASSWORD_ENV).is_ok() {
+        return read_password(password_file, "");
+    }
+    loop {
+        let password =
+            rpassword::prompt_password("New password: ").expect("Failed to read password");
+        let confirm =
+            rpassword::prompt_password("Confirm password: ").expect("Failed to read password");
+        if password.is_empty() {
+            println!("Password cannot be empty.");
+        } else if password.ne(&confirm) {
+            println!("Passwords do not match.");
+        } else {
+            return password;
+        }
+    }
+}
+
+/// Default directory for keystores created by `ore keys`.
+pub fn keystore_dir() -> PathBuf {
+    ore_dir().join("keystore")
+}
+
+/// Derives the cipher key with argon2id.
+fn derive_key(password: &str, salt: &[u8], rounds: u32, memory: u32) -> Result<[u8; 32], String> {
+    let params = Params::new(memory, rounds, 1, Some(32)).map_err(|err| err.to_string())?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut key)
+        .map_err(|err| err.to_string())?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn encrypt_decrypt_round_trip() {
+        let keypair = Keypair::new();
+        let keystore = Keystore::encrypt(&keypair, "hunter2");
+        assert_eq!(keystore.pubkey, keypair.pubkey().to_string());
+        let decrypted = keystore.decrypt("hunter2").unwrap();
+        assert_eq!(decrypted.to_bytes(), keypair.to_bytes());
+        assert_eq!(keystore.decrypt("hunter3").unwrap_err(), "Wrong password");
+    }
+
+    #[test]
+    fn write_is_owner_only() {
+        let path = std::env::temp_dir().join(format!("ore-keystore-{}.json", std::process::id()));
+        let keystore = Keystore::encrypt(&Keypair::new(), "hunter2");
+        keystore.write(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(Keystore::read(&path).unwrap().pubkey, keystore.pubkey);
+        fs::remove_file(&path).unwrap();
+    }
+}
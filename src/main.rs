@@ -1,45 +1,100 @@
+mod accounts;
+mod affinity;
+mod alt;
+mod api;
 mod args;
+mod attribution;
 mod balance;
 mod benchmark;
+mod broadcast;
+mod budget;
 mod busses;
 mod claim;
 mod close;
 mod config;
+mod control;
 mod cu_limits;
+mod estimate;
+mod export;
+mod funding;
+mod history;
 #[cfg(feature = "admin")]
 mod initialize;
+mod keys;
+mod keystore;
 mod mine;
+mod nonce;
+mod notify;
 mod open;
+mod pool;
+mod profile;
 mod rewards;
+mod schedule;
 mod send_and_confirm;
+mod shutdown;
+mod signer;
 mod stake;
+mod tui;
 mod upgrade;
 mod utils;
 
-use std::sync::Arc;
+use std::{
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+};
 
 use args::*;
-use clap::{command, Parser, Subcommand};
+use clap::{Parser, Subcommand};
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{
-    commitment_config::CommitmentConfig,
-    signature::{read_keypair_file, Keypair},
+    address_lookup_table::AddressLookupTableAccount, commitment_config::CommitmentConfig,
+    pubkey::Pubkey, signature::Keypair,
 };
 
 struct Miner {
     pub keypair_filepath: Option<String>,
-    pub priority_fee: u64,
+    pub account_index: Option<u32>,
+    pub password_file: Option<String>,
+    pub priority_fee: AtomicU64,
+    pub min_sol_balance: f64,
+    pub lookup_table: Option<Pubkey>,
+    lookup_table_account: OnceLock<AddressLookupTableAccount>,
+    pub yes: bool,
     pub rpc_client: Arc<RpcClient>,
+    pub notifier: OnceLock<notify::Notifier>,
+    pub funding: OnceLock<funding::Funding>,
+    pub budget: OnceLock<Mutex<budget::Budget>>,
+    signer: OnceLock<Keypair>,
+}
+
+/// The settings a [`Miner`] is created with, taken from the global arguments.
+struct MinerConfig {
+    keypair_filepath: Option<String>,
+    account_index: Option<u32>,
+    password_file: Option<String>,
+    priority_fee: u64,
+    min_sol_balance: f64,
+    lookup_table: Option<Pubkey>,
+    yes: bool,
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
+    #[command(about = "Manage address lookup tables for large transactions")]
+    Alt(AltArgs),
+
     #[command(about = "Fetch an account balance")]
     Balance(BalanceArgs),
 
     #[command(about = "Benchmark your hashpower")]
     Benchmark(BenchmarkArgs),
 
+    #[command(about = "Submit a transaction signed with --sign-only")]
+    Broadcast(BroadcastArgs),
+
     #[command(about = "Fetch the bus account balances")]
     Busses(BussesArgs),
 
@@ -52,8 +107,26 @@ enum Commands {
     #[command(about = "Fetch the program config")]
     Config(ConfigArgs),
 
+    #[command(about = "Control a running miner through its control socket")]
+    Ctl(CtlArgs),
+
+    #[command(about = "Estimate expected earnings and fees for a hashrate")]
+    Estimate(EstimateArgs),
+
+    #[command(about = "Export your ORE transactions for accounting")]
+    Export(ExportArgs),
+
+    #[command(about = "Summarize your recorded mining rounds")]
+    History(HistoryArgs),
+
+    #[command(about = "Manage encrypted keystores")]
+    Keys(KeysArgs),
+
     #[command(about = "Start mining")]
-    Mine(MineArgs),
+    Mine(Box<MineArgs>),
+
+    #[command(about = "Manage durable nonce accounts")]
+    Nonce(NonceArgs),
 
     #[command(about = "Fetch the current reward rate for each difficulty level")]
     Rewards(RewardsArgs),
@@ -91,12 +164,28 @@ struct Args {
 
     #[arg(
         long,
-        value_name = "KEYPAIR_FILEPATH",
-        help = "Filepath to keypair to use",
+        value_name = "KEYPAIR_SOURCE",
+        help = "Keypair to use: a filepath, or a file://, stdin://, prompt:// or mnemonic:// URI",
         global = true
     )]
     keypair: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "INDEX",
+        help = "Account index substituted for N in a mnemonic derivation path, or a START..END range to run the command with each of those wallets",
+        global = true
+    )]
+    account_index: Option<AccountIndex>,
+
+    #[arg(
+        long,
+        value_name = "FILEPATH",
+        help = "Filepath to read the keystore password from. Falls back to ORE_KEYSTORE_PASSWORD, then a prompt.",
+        global = true
+    )]
+    password_file: Option<String>,
+
     #[arg(
         long,
         value_name = "MICROLAMPORTS",
@@ -106,6 +195,32 @@ struct Args {
     )]
     priority_fee: u64,
 
+    #[arg(
+        long,
+        value_name = "SOL",
+        help = "Refuse to send transactions when the payer balance is at or below this amount",
+        default_value = "0.005",
+        global = true
+    )]
+    min_sol_balance: f64,
+
+    #[arg(
+        long,
+        short = 'y',
+        visible_alias = "no-confirm",
+        help = "Skip confirmation prompts",
+        global = true
+    )]
+    yes: bool,
+
+    #[arg(
+        long,
+        value_name = "LOOKUP_TABLE_ADDRESS",
+        help = "Address lookup table to use for transactions too large to send as legacy transactions",
+        global = true
+    )]
+    lookup_table: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -130,35 +245,82 @@ async fn main() {
     let cluster = args.rpc.unwrap_or(cli_config.json_rpc_url);
     let default_keypair = args.keypair.unwrap_or(cli_config.keypair_path);
     let rpc_client = RpcClient::new_with_commitment(cluster, CommitmentConfig::confirmed());
+    let lookup_table = args.lookup_table.map(|address| {
+        Pubkey::from_str(&address).unwrap_or_else(|_| {
+            eprintln!("error: Invalid lookup table address `{}`", address);
+            std::process::exit(1);
+        })
+    });
+
+    // Run the command with each wallet of an account index range in its own process
+    let account_index = match args.account_index {
+        Some(AccountIndex::Range(range)) => {
+            accounts::run_each_account(&default_keypair, range, &args.command);
+            return;
+        }
+        Some(AccountIndex::One(index)) => Some(index),
+        None => None,
+    };
 
     let miner = Arc::new(Miner::new(
         Arc::new(rpc_client),
-        args.priority_fee,
-        Some(default_keypair),
+        MinerConfig {
+            keypair_filepath: Some(default_keypair),
+            account_index,
+            password_file: args.password_file,
+            priority_fee: args.priority_fee,
+            min_sol_balance: args.min_sol_balance,
+            lookup_table,
+            yes: args.yes,
+        },
     ));
 
     // Execute user command.
     match args.command {
+        Commands::Alt(args) => {
+            miner.alt(args).await;
+        }
         Commands::Balance(args) => {
             miner.balance(args).await;
         }
         Commands::Benchmark(args) => {
             miner.benchmark(args).await;
         }
+        Commands::Broadcast(args) => {
+            miner.broadcast(args).await;
+        }
         Commands::Busses(_) => {
             miner.busses().await;
         }
         Commands::Claim(args) => {
-            miner.claim(args).await;
+            miner.claim(args, false).await;
         }
-        Commands::Close(_) => {
-            miner.close().await;
+        Commands::Close(args) => {
+            miner.close(args).await;
         }
         Commands::Config(_) => {
             miner.config().await;
         }
+        Commands::Ctl(args) => {
+            miner.ctl(args).await;
+        }
+        Commands::Estimate(args) => {
+            miner.estimate(args).await;
+        }
+        Commands::Export(args) => {
+            miner.export(args).await;
+        }
+        Commands::History(args) => {
+            miner.history(args).await;
+        }
+        Commands::Keys(args) => {
+            miner.keys(args).await;
+        }
         Commands::Mine(args) => {
-            miner.mine(args).await;
+            miner.mine(*args).await;
+        }
+        Commands::Nonce(args) => {
+            miner.nonce(args).await;
         }
         Commands::Rewards(_) => {
             miner.rewards().await;
@@ -177,23 +339,38 @@ async fn main() {
 }
 
 impl Miner {
-    pub fn new(
-        rpc_client: Arc<RpcClient>,
-        priority_fee: u64,
-        keypair_filepath: Option<String>,
-    ) -> Self {
+    pub fn new(rpc_client: Arc<RpcClient>, config: MinerConfig) -> Self {
         Self {
             rpc_client,
-            keypair_filepath,
-            priority_fee,
+            keypair_filepath: config.keypair_filepath,
+            account_index: config.account_index,
+            password_file: config.password_file,
+            priority_fee: AtomicU64::new(config.priority_fee),
+            min_sol_balance: config.min_sol_balance,
+            lookup_table: config.lookup_table,
+            lookup_table_account: OnceLock::new(),
+            yes: config.yes,
+            notifier: OnceLock::new(),
+            funding: OnceLock::new(),
+            budget: OnceLock::new(),
+            signer: OnceLock::new(),
         }
     }
 
+    /// The priority fee in microlamports, which the mining loop can change while it runs.
+    pub fn priority_fee(&self) -> u64 {
+        self.priority_fee.load(Ordering::Relaxed)
+    }
+
     pub fn signer(&self) -> Keypair {
-        match self.keypair_filepath.clone() {
-            Some(filepath) => read_keypair_file(filepath.clone())
-                .expect(format!("No keypair found at {}", filepath).as_str()),
-            None => panic!("No keypair provided"),
-        }
+        // Decrypt once and keep the keypair in memory for the rest of the session.
+        self.signer
+            .get_or_init(|| match self.keypair_filepath.clone() {
+                Some(source) => {
+                    signer::read_signer(&source, &self.password_file, self.account_index)
+                }
+                None => panic!("No keypair provided"),
+            })
+            .insecure_clone()
     }
 }
@@ -1,156 +1,931 @@
-use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
-use std::thread;
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    path::PathBuf,
+    str::FromStr,
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Local};
 
 use colored::*;
-use drillx::{
-    equix::{self},
-    Hash, Solution,
-};
+use drillx::Solution;
 use ore_api::{
-    consts::{BUS_ADDRESSES, BUS_COUNT, EPOCH_DURATION},
+    consts::{BUS_ADDRESSES, BUS_COUNT, EPOCH_DURATION, MINT_ADDRESS, ONE_MINUTE, TOLERANCE},
     state::{Config, Proof},
 };
+use ore_utils::AccountDeserialize;
 use rand::Rng;
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
 use solana_program::pubkey::Pubkey;
 use solana_rpc_client::spinner;
-use solana_sdk::signer::Signer;
+use solana_sdk::{
+    native_token::{lamports_to_sol, sol_to_lamports},
+    signature::{Signature, Signer},
+};
+use spl_associated_token_account::get_associated_token_address;
+use tokio::task::JoinHandle;
 
 use crate::{
-    args::MineArgs,
+    affinity::WorkerConfig,
+    api::{self, SharedRoundStatus},
+    args::{MineArgs, Threads},
+    attribution::MineReward,
+    budget::{Budget, Overspend},
+    control::{default_control_socket, serve, ControlSocket, ControlState, SharedControlState},
+    cu_limits::{CU_LIMIT_CLAIM, CU_LIMIT_SUBMIT},
+    estimate::{break_even_difficulty, hash_reward, transaction_fee},
+    funding::Funding,
+    history::{rounds_path, RoundRecord},
+    notify::{Event, Notifier},
+    pool::{WorkerPool, WorkerResult},
+    profile::Profile,
     send_and_confirm::ComputeBudget,
-    utils::{amount_u64_to_string, get_clock, get_config, get_proof_with_authority, proof_pubkey},
+    shutdown::Shutdown,
+    tui::{print_line, Dashboard},
+    utils::{
+        amount_f64_to_u64, amount_u64_to_string, get_clock, get_config, get_proof_with_authority,
+        proof_pubkey, stake_multiplier, try_get_proof_with_authority,
+    },
     Miner,
 };
 
+/// How often to check whether the pool has found a solution.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often to check whether the proof's challenge changed mid-round.
+const PROOF_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Longest to wait, after Ctrl-C, for the round's best hash to become submittable.
+const SHUTDOWN_SUBMIT_WAIT: i64 = 10;
+
+/// Longest to wait, after Ctrl-C, for a pending transaction to confirm.
+const SHUTDOWN_CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Environment variable holding the HTTP API token, if not given with --http-token.
+const HTTP_TOKEN_ENV: &str = "ORE_HTTP_TOKEN";
+
+/// The best hash found in a round and what it took to find it.
+struct FoundHash {
+    challenge: [u8; 32],
+    best: WorkerResult,
+    hashes: u64,
+}
+
+/// A submitted round, recorded once its reward has been looked up.
+struct Submitted {
+    proof: Proof,
+    found: FoundHash,
+    cutoff_time: u64,
+    bus: usize,
+
+    /// Estimated fee of the transaction, in lamports.
+    fee: u64,
+    result: ClientResult<Signature>,
+    reward: Option<MineReward>,
+}
+
+/// Totals for the mining session, printed on exit.
+#[derive(Default)]
+struct Session {
+    rounds: u64,
+    landed: u64,
+    rewards: u64,
+
+    /// Estimated fees of landed transactions, in lamports.
+    fees: u64,
+    skipped_unprofitable: u64,
+    skipped_over_budget: u64,
+}
+
+/// What `find_hash` looks for.
+#[derive(Clone, Copy)]
+struct Search {
+    proof: Proof,
+
+    /// Seconds until the round's cutoff, for display.
+    cutoff_time: u64,
+    min_difficulty: u32,
+
+    /// When to settle for the best hash so far, even if it doesn't beat `min_difficulty`.
+    deadline: Option<Instant>,
+
+    /// Carry on from where the workers left off on the same challenge, rather than
+    /// starting over.
+    resume: bool,
+    show_progress: bool,
+}
+
+impl FoundHash {
+    fn solution(&self) -> Solution {
+        Solution::new(self.best.digest, self.best.nonce.to_le_bytes())
+    }
+}
+
 impl Miner {
-    pub async fn mine(&self, args: MineArgs) {
-        // Register, if needed.
+    pub async fn mine(self: Arc<Self>, args: MineArgs) {
         let signer = self.signer();
+
+        // Auto-staking would stake rewards claimed to the miner's wallet straight back
+        if args.auto_stake.is_some()
+            && (args.auto_claim_above.is_some() || args.auto_claim_schedule.is_some())
+            && args
+                .claim_to
+                .iter()
+                .all(|to| to.eq(&signer.pubkey().to_string()))
+        {
+            println!(
+                "{} --auto-stake would stake auto-claimed rewards straight back. Pass --claim-to another wallet.",
+                "ERROR".bold().red()
+            );
+            std::process::exit(1);
+        }
+
+        // Register, if needed.
         self.open().await;
 
+        // Check the lookup table up front, rather than when a transaction needs it
+        self.load_lookup_table().await;
+
         // Check num threads
-        self.check_num_cores(args.threads);
+        let mut threads = resolve_threads(args.threads);
+        self.check_num_cores(threads);
+        let worker_config = WorkerConfig::new(
+            args.pin_cores.clone().map(|cores| cores.0),
+            args.reserve_cores,
+            args.nice,
+        );
+        let mut pool = WorkerPool::new(threads, worker_config.clone());
+        let mut min_difficulty = args.nandu as u32;
+        let mut last_auto_stake = None;
+        let claim_to = match &args.claim_to {
+            Some(to) => Pubkey::from_str(to).expect("Failed to parse wallet address"),
+            None => signer.pubkey(),
+        };
+        let mut next_claim = args
+            .auto_claim_schedule
+            .as_ref()
+            .and_then(|schedule| schedule.next_after(Local::now()));
+        let (control, _control_socket) = (args.daemon || args.control_socket.is_some())
+            .then(|| self.start_control(&args, threads, min_difficulty))
+            .unzip();
+        let round = SharedRoundStatus::default();
+        if let Some(addr) = args.http_addr {
+            self.start_api(addr, &args, round.clone());
+        }
+        if let Some(notifier) = Notifier::from_args(signer.pubkey(), &args, self.min_sol_balance) {
+            self.notifier.set(notifier).ok();
+        }
+        if let Some(funding) = Funding::from_args(&args, &self.password_file) {
+            self.funding.set(funding).ok();
+        }
+        let payers: Vec<Pubkey> = std::iter::once(signer.pubkey())
+            .chain(self.funding.get().and_then(Funding::funder))
+            .collect();
+        if let Some(budget) = Budget::from_args(&payers, &args) {
+            self.budget.set(Mutex::new(budget)).ok();
+        }
+        let mut failures = 0;
+        let mut last_min_difficulty = None;
+        let mut pending = VecDeque::new();
+        let mut held: Option<FoundHash> = None;
+        let mut session = Session::default();
+        let timer = Instant::now();
+        let shutdown = Shutdown::listen(args.tui);
+        let mut dashboard = args.tui.then(|| Dashboard::new(signer.pubkey()));
 
         // Start mining loop
-        loop {
+        while !shutdown.requested() {
+            // Apply settings changed over the control socket
+            if let Some(control) = control.as_ref() {
+                let Some(state) = self
+                    .wait_while_paused(
+                        control,
+                        &args,
+                        claim_to,
+                        &mut next_claim,
+                        &shutdown,
+                        &mut dashboard,
+                    )
+                    .await
+                else {
+                    break;
+                };
+                if state.threads.ne(&threads) {
+                    threads = state.threads;
+                    self.check_num_cores(threads);
+                    pool = WorkerPool::new(threads, worker_config.clone());
+                }
+                min_difficulty = state.min_difficulty;
+                self.priority_fee
+                    .store(state.priority_fee, Ordering::Relaxed);
+            }
+
             // Fetch proof
             let proof = get_proof_with_authority(&self.rpc_client, signer.pubkey()).await;
-            println!(
-                "\nStake balance: {} ORE",
-                amount_u64_to_string(proof.balance)
-            );
-println!("{:?}", args);
+            let config = get_config(&self.rpc_client).await;
+            self.check_difficulty(&args, config, &mut last_min_difficulty);
+            match dashboard.as_mut() {
+                Some(dashboard) => self.refresh_dashboard(dashboard, proof, config).await,
+                None => println!(
+                    "\nStake balance: {} ORE ({:.4}x multiplier)",
+                    amount_u64_to_string(proof.balance),
+                    stake_multiplier(proof.balance, config.top_balance)
+                ),
+            }
+
             // Calc cutoff time
             let cutoff_time = self.get_cutoff(proof, args.buffer_time).await;
 
-            // Run drillx
-            let config = get_config(&self.rpc_client).await;
-println!("{:?}", config);			
-			
-			
-            let solution = Self::find_hash_par(
+            // Run drillx, holding out for a hash worth its fee if an ORE price is given
+            let multiplier = stake_multiplier(proof.balance, config.top_balance);
+            let fee = transaction_fee(self.priority_fee(), CU_LIMIT_SUBMIT);
+            let unprofitable = |difficulty: u32| {
+                let reward = hash_reward(difficulty, &config, multiplier);
+                args.ore_price
+                    .filter(|ore_price| sol_to_lamports(reward * ore_price).lt(&fee))
+            };
+            let holdout_deadline = Instant::now()
+                + Duration::from_secs(cutoff_time + args.unprofitable_holdout * ONE_MINUTE as u64);
+            // Carry on from a round skipped for want of a hash worth its fee
+            let mut found = held
+                .take()
+                .filter(|held| held.challenge.eq(&proof.challenge));
+            let mut search = Search {
                 proof,
                 cutoff_time,
-                args.threads,
-                args.nandu as u32,
-            )
-            .await;
+                min_difficulty,
+                deadline: None,
+                resume: found.is_some(),
+                show_progress: !args.tui && !args.daemon,
+            };
+            let mut hashes = 0;
+            while let Some(next) = self
+                .find_hash(&mut pool, search, &round, &shutdown, &mut dashboard)
+                .await
+            {
+                // A worker can report a hash below the best one found before holding out
+                hashes += next.hashes;
+                let best = match found.take() {
+                    Some(found) if found.best.difficulty.ge(&next.best.difficulty) => found,
+                    _ => next,
+                };
+                let (difficulty, challenge) = (best.best.difficulty, best.challenge);
+                found = Some(best);
+                let Some(ore_price) = unprofitable(difficulty) else {
+                    break;
+                };
+                let remaining = holdout_deadline.saturating_duration_since(Instant::now());
+                if shutdown.requested() || remaining.is_zero() {
+                    break;
+                }
+
+                // Keep hashing the same challenge, from where the workers left off
+                let break_even = break_even_difficulty(fee, ore_price, &config, multiplier);
+                log(
+                    &mut dashboard,
+                    format!(
+                        "Holding back difficulty {} hash: its reward is worth less than the {} SOL fee. Mining for difficulty {} for up to {} sec...",
+                        difficulty,
+                        lamports_to_sol(fee),
+                        break_even,
+                        remaining.as_secs()
+                    ),
+                );
+                search = Search {
+                    proof: Proof { challenge, ..proof },
+                    cutoff_time: remaining.as_secs(),
+                    min_difficulty: break_even.saturating_sub(1),
+                    deadline: Some(holdout_deadline),
+                    resume: true,
+                    ..search
+                };
+            }
+            let Some(found) = found else {
+                break;
+            };
+            let found = FoundHash { hashes, ..found };
+            if unprofitable(found.best.difficulty).is_some() {
+                if shutdown.requested() {
+                    log(
+                        &mut dashboard,
+                        "Discarding best hash: its reward is worth less than the fee".to_string(),
+                    );
+                    break;
+                }
+                if !args.submit_unprofitable {
+                    session.skipped_unprofitable += 1;
+                    round.lock().unwrap().skipped_unprofitable += 1;
+                    log(
+                        &mut dashboard,
+                        format!(
+                            "No hash worth the fee found in time, skipping the round and carrying on from the difficulty {} hash",
+                            found.best.difficulty
+                        ),
+                    );
+                    held = Some(found);
+                    continue;
+                }
+                log(
+                    &mut dashboard,
+                    format!(
+                        "No hash worth the fee found in time, submitting the difficulty {} hash anyway",
+                        found.best.difficulty
+                    ),
+                );
+            }
+
+            // On Ctrl-C, only submit the hash if the program will accept it
+            if shutdown.requested()
+                && !self
+                    .can_submit_early(proof, config, &found, &mut dashboard)
+                    .await
+            {
+                break;
+            }
 
             // Submit most difficult hash
-            let mut compute_budget = 500_000;
+            let mut compute_budget = CU_LIMIT_SUBMIT;
             let mut ixs = vec![ore_api::instruction::auth(proof_pubkey(signer.pubkey()))];
             if self.should_reset(config).await && rand::thread_rng().gen_range(0..100).eq(&0) {
                 compute_budget += 100_000;
                 ixs.push(ore_api::instruction::reset(signer.pubkey()));
             }
+            let bus = find_bus();
             ixs.push(ore_api::instruction::mine(
                 signer.pubkey(),
                 signer.pubkey(),
-                find_bus(),
-                solution,
+                BUS_ADDRESSES[bus],
+                found.solution(),
             ));
-            self.send_and_confirm(&ixs, ComputeBudget::Fixed(compute_budget), false)
-                .await
-                .ok();
+
+            // Stay within the fee budgets, keeping the hash while waiting for the next day
+            let fee = transaction_fee(self.priority_fee(), compute_budget);
+            if self.check_budget(fee).is_err() {
+                session.skipped_over_budget += 1;
+                round.lock().unwrap().skipped_over_budget += 1;
+                if !self
+                    .wait_for_budget(fee, &args, &shutdown, &mut dashboard)
+                    .await
+                {
+                    break;
+                }
+            }
+
+            let submit = self.send_and_confirm(&ixs, ComputeBudget::Fixed(compute_budget), false);
+            let result = tokio::select! {
+                result = submit => result,
+                _ = shutdown.deadline(SHUTDOWN_CONFIRM_TIMEOUT) => Err(ClientError {
+                    request: None,
+                    kind: ClientErrorKind::Custom("Timed out waiting for confirmation".into()),
+                }),
+            };
+
+            // Start hashing the next challenge while the reward is looked up
+            let latest = match result {
+                Ok(_) => try_get_proof_with_authority(&self.rpc_client, signer.pubkey()).await,
+                Err(_) => None,
+            };
+            if let Some(latest) = latest.filter(|latest| latest.challenge.ne(&proof.challenge)) {
+                pool.prefetch(latest.challenge, min_difficulty);
+            }
+
+            // Attribute the reward to the hash which earned it in the background, and record
+            // the rounds whose reward is known
+            let miner = self.clone();
+            let mut submitted = Submitted {
+                proof,
+                found,
+                cutoff_time,
+                bus,
+                fee,
+                result,
+                reward: None,
+            };
+            pending.push_back(tokio::spawn(async move {
+                if let Ok(sig) = &submitted.result {
+                    submitted.reward = miner
+                        .get_mine_reward(sig, proof, latest, submitted.found.best.difficulty)
+                        .await;
+                }
+                submitted
+            }));
+            while let Some(submitted) = next_attributed(&mut pending, false).await {
+                self.finish_round(
+                    submitted,
+                    &args,
+                    &mut session,
+                    &mut failures,
+                    &round,
+                    &mut dashboard,
+                );
+            }
+            if shutdown.requested() {
+                break;
+            }
+
+            // Claim rewards now that the solution is in
+            let requested = control.as_ref().is_some_and(|control| {
+                std::mem::take(&mut control.lock().unwrap().claim_requested)
+            });
+            if requested || args.auto_claim_above.is_some() || args.auto_claim_schedule.is_some() {
+                self.auto_claim(&args, claim_to, &mut next_claim, requested, &mut dashboard)
+                    .await;
+            }
+
+            // Stake wallet ORE now that the solution is in
+            if let Some(threshold) = args.auto_stake {
+                self.auto_stake(
+                    amount_f64_to_u64(threshold),
+                    &args,
+                    &mut last_auto_stake,
+                    &mut dashboard,
+                )
+                .await;
+            }
+        }
+
+        // Record the rounds still being attributed
+        while let Some(submitted) = next_attributed(&mut pending, true).await {
+            self.finish_round(
+                submitted,
+                &args,
+                &mut session,
+                &mut failures,
+                &round,
+                &mut dashboard,
+            );
         }
+
+        // Leave the dashboard before printing the summary
+        drop(dashboard);
+        print_session(&session, timer.elapsed());
     }
 
-        // 异步方法：并行查找哈希
-async fn find_hash_par(
-    proof: Proof,
-    cutoff_time: u64,
-    threads: u64,
-    min_difficulty: u32,
-) -> Solution {
-    let progress_bar = Arc::new(spinner::new_progress_bar());
-    progress_bar.set_message("Mining...");
-
-    let found_solution = Arc::new(AtomicBool::new(false));
-    let handles: Vec<_> = (0..threads)
-        .map(|i| {
-            let proof = proof.clone();
-            let progress_bar = progress_bar.clone();
-            let found_solution = found_solution.clone();
-
-            let mut memory = equix::SolverMemory::new();
-            thread::spawn(move || {
-				let mut nonce = u64::MAX.saturating_div(threads).saturating_mul(i); // 计算初始nonce
-               // let mut nonce = i; // 每个线程从不同的nonce开始
-                let mut best_difficulty = 0;
-                let mut best_hash = Hash::default();
-
-                while !found_solution.load(Ordering::Relaxed) {
-                    if let Ok(hx) = drillx::hash_with_memory(
-                        &mut memory,
-                        &proof.challenge,
-                        &nonce.to_le_bytes(),
-                    ) {
-                        let difficulty = hx.difficulty();
-                        if difficulty > min_difficulty {
-                            found_solution.store(true, Ordering::Relaxed);
-                            println!("Success! Thread {} found a difficulty: {:?}", i, difficulty);
-                            return (nonce, difficulty, hx);
+    /// Whether the program will accept a hash submitted before the round's cutoff, waiting
+    /// a few seconds if it soon will.
+    async fn can_submit_early(
+        &self,
+        proof: Proof,
+        config: Config,
+        found: &FoundHash,
+        dashboard: &mut Option<Dashboard>,
+    ) -> bool {
+        if (found.best.difficulty as u64).lt(&config.min_difficulty) {
+            log(
+                dashboard,
+                format!(
+                    "Discarding best hash: difficulty {} is below the minimum of {}",
+                    found.best.difficulty, config.min_difficulty
+                ),
+            );
+            return false;
+        }
+
+        // Hashes submitted too soon after the last one are rejected as spam
+        let clock = get_clock(&self.rpc_client).await;
+        let wait = proof
+            .last_hash_at
+            .saturating_add(ONE_MINUTE)
+            .saturating_sub(TOLERANCE)
+            .saturating_sub(clock.unix_timestamp);
+        if wait.gt(&SHUTDOWN_SUBMIT_WAIT) {
+            log(
+                dashboard,
+                format!(
+                    "Discarding best hash: it can't be submitted for another {} sec",
+                    wait
+                ),
+            );
+            return false;
+        }
+        if wait.gt(&0) {
+            log(
+                dashboard,
+                format!("Waiting {} sec to submit best hash...", wait),
+            );
+            tokio::time::sleep(Duration::from_secs(wait as u64)).await;
+        }
+        true
+    }
+
+    /// Starts listening for `ore ctl` commands, exiting if the socket can't be opened.
+    fn start_control(
+        &self,
+        args: &MineArgs,
+        threads: u64,
+        min_difficulty: u32,
+    ) -> (SharedControlState, ControlSocket) {
+        let path = args
+            .control_socket
+            .as_ref()
+            .map_or_else(default_control_socket, PathBuf::from);
+        let state = Arc::new(Mutex::new(ControlState {
+            paused: false,
+            threads,
+            min_difficulty,
+            priority_fee: self.priority_fee(),
+            claim_requested: false,
+        }));
+        let socket = serve(&path, state.clone()).unwrap_or_else(|err| {
+            eprintln!(
+                "{} Failed to listen on {}: {}",
+                "ERROR".bold().red(),
+                path.display(),
+                err
+            );
+            std::process::exit(1);
+        });
+        println!("Listening for commands on {}", path.display());
+        (state, socket)
+    }
+
+    /// Starts serving the miner's status over HTTP, exiting if the address can't be bound.
+    fn start_api(self: &Arc<Self>, addr: SocketAddr, args: &MineArgs, round: SharedRoundStatus) {
+        let token = args
+            .http_token
+            .clone()
+            .or_else(|| std::env::var(HTTP_TOKEN_ENV).ok());
+        let claim_reserve = amount_f64_to_u64(args.claim_reserve);
+        if let Err(err) = api::serve(addr, self.clone(), round, token, claim_reserve) {
+            eprintln!(
+                "{} Failed to listen on {}: {}",
+                "ERROR".bold().red(),
+                addr,
+                err
+            );
+            std::process::exit(1);
+        }
+        println!("Serving the HTTP API on http://{}", addr);
+    }
+
+    /// Claims rewards above the reserve if a claim was requested over the control socket,
+    /// the stake balance crossed the threshold or a scheduled claim is due.
+    async fn auto_claim(
+        &self,
+        args: &MineArgs,
+        claim_to: Pubkey,
+        next_claim: &mut Option<DateTime<Local>>,
+        requested: bool,
+        dashboard: &mut Option<Dashboard>,
+    ) {
+        let Some(proof) =
+            try_get_proof_with_authority(&self.rpc_client, self.signer().pubkey()).await
+        else {
+            return;
+        };
+        let now = Local::now();
+        let scheduled = next_claim.is_some_and(|next_claim| now.ge(&next_claim));
+        if scheduled {
+            *next_claim = args
+                .auto_claim_schedule
+                .as_ref()
+                .and_then(|schedule| schedule.next_after(now));
+        }
+        let trigger = if requested {
+            "control"
+        } else if args
+            .auto_claim_above
+            .is_some_and(|above| proof.balance.gt(&amount_f64_to_u64(above)))
+        {
+            "threshold"
+        } else if scheduled {
+            "schedule"
+        } else {
+            return;
+        };
+
+        // Leave the reserve staked
+        let amount = proof
+            .balance
+            .saturating_sub(amount_f64_to_u64(args.claim_reserve));
+        if amount == 0 {
+            return;
+        }
+        log(
+            dashboard,
+            format!(
+                "Auto-claiming {} ORE to {} ({})",
+                amount_u64_to_string(amount),
+                claim_to,
+                trigger
+            ),
+        );
+        self.send_claim(claim_to, amount, trigger).await.ok();
+    }
+
+    /// Stakes the wallet's ORE if it holds more than `threshold` and the interval has passed.
+    async fn auto_stake(
+        &self,
+        threshold: u64,
+        args: &MineArgs,
+        last_stake: &mut Option<Instant>,
+        dashboard: &mut Option<Dashboard>,
+    ) {
+        let interval = Duration::from_secs(args.auto_stake_interval);
+        if last_stake.is_some_and(|last_stake| last_stake.elapsed().lt(&interval)) {
+            return;
+        }
+        let signer = self.signer();
+        let sender = get_associated_token_address(&signer.pubkey(), &MINT_ADDRESS);
+        let Ok(Some(token_account)) = self.rpc_client.get_token_account(&sender).await else {
+            return;
+        };
+        let Ok(amount) = token_account.token_amount.amount.parse::<u64>() else {
+            return;
+        };
+        if amount.le(&threshold) {
+            return;
+        }
+
+        // Leave the fee budgets to the mining transactions
+        if self
+            .check_budget(transaction_fee(self.priority_fee(), CU_LIMIT_CLAIM))
+            .is_err()
+        {
+            log(
+                dashboard,
+                "Skipping auto-stake: its fee doesn't fit in the fee budget".to_string(),
+            );
+            return;
+        }
+        *last_stake = Some(Instant::now());
+        if args.auto_stake_dry_run {
+            log(
+                dashboard,
+                format!(
+                    "Auto-stake: would stake {} ORE (dry run)",
+                    amount_u64_to_string(amount)
+                ),
+            );
+            return;
+        }
+        log(
+            dashboard,
+            format!("Auto-staking {} ORE", amount_u64_to_string(amount)),
+        );
+
+        // Don't wait for confirmation, so the next round starts on time. The fee is recorded
+        // as the transaction is sent.
+        let ix = ore_api::instruction::stake(signer.pubkey(), sender, amount);
+        self.send_and_confirm(&[ix], ComputeBudget::Fixed(CU_LIMIT_CLAIM), true)
+            .await
+            .ok();
+    }
+
+    /// Hashes the proof's challenge on the worker pool until a hash beats `min_difficulty`,
+    /// switching challenge if the proof changes in the meantime.
+    async fn find_hash(
+        &self,
+        pool: &mut WorkerPool,
+        search: Search,
+        round: &SharedRoundStatus,
+        shutdown: &Shutdown,
+        dashboard: &mut Option<Dashboard>,
+    ) -> Option<FoundHash> {
+        let Search {
+            proof,
+            cutoff_time,
+            min_difficulty,
+            deadline,
+            resume,
+            show_progress,
+        } = search;
+        let progress_bar = show_progress.then(|| {
+            let progress_bar = spinner::new_progress_bar();
+            progress_bar.set_message("Mining...");
+            progress_bar
+        });
+        let timer = Instant::now();
+        let thread_start_hashes = pool.hashes();
+        let start_hashes: u64 = thread_start_hashes.iter().sum();
+        let mut challenge = proof.challenge;
+        if let Some(dashboard) = dashboard.as_mut() {
+            dashboard.challenge = Some(challenge);
+            dashboard.cutoff = Some(timer + Duration::from_secs(cutoff_time));
+        }
+        {
+            let mut round = round.lock().unwrap();
+            round.challenge = Some(bs58::encode(challenge).into_string());
+            round.cutoff_at = Some(chrono::Utc::now().timestamp() + cutoff_time as i64);
+            round.min_difficulty = min_difficulty;
+            round.best_difficulty = 0;
+        }
+        let mut last_proof_check = Instant::now();
+        if resume {
+            pool.resume(challenge, min_difficulty);
+        } else {
+            pool.start(challenge, min_difficulty);
+        }
+        let best = loop {
+            if let Some(best) = pool.poll() {
+                break best;
+            }
+
+            // Stop at the best hash so far on Ctrl-C, or once the deadline passes
+            if shutdown.requested() || deadline.is_some_and(|deadline| Instant::now().ge(&deadline))
+            {
+                pool.stop();
+                match pool.best() {
+                    Some(best) => break best,
+                    None => {
+                        if let Some(progress_bar) = progress_bar {
+                            progress_bar.finish_and_clear();
+                        }
+                        return None;
+                    }
+                }
+            }
+
+            // Switch to the new challenge as soon as the proof updates
+            if last_proof_check.elapsed().ge(&PROOF_CHECK_INTERVAL) {
+                last_proof_check = Instant::now();
+                if let Ok(data) = self
+                    .rpc_client
+                    .get_account_data(&proof_pubkey(proof.authority))
+                    .await
+                {
+                    if let Ok(latest) = Proof::try_from_bytes(&data) {
+                        if latest.challenge.ne(&challenge) {
+                            challenge = latest.challenge;
+                            pool.start(challenge, min_difficulty);
+                            if let Some(dashboard) = dashboard.as_mut() {
+                                dashboard.challenge = Some(challenge);
+                            }
+                            round.lock().unwrap().challenge =
+                                Some(bs58::encode(challenge).into_string());
                         }
                     }
-                    nonce += threads; // 递增nonce，确保每个线程处理不同的nonce
                 }
-                (0, 0, Hash::default())
-            })
+            }
+
+            let elapsed = timer.elapsed().as_secs_f64();
+            let best_difficulty = pool.best().map_or(0, |best| best.difficulty);
+            let thread_hashrates: Vec<f64> = pool
+                .hashes()
+                .iter()
+                .zip(thread_start_hashes.iter())
+                .map(|(hashes, start)| (hashes - start) as f64 / elapsed)
+                .collect();
+            let hashrate = thread_hashrates.iter().sum::<f64>();
+            {
+                let mut round = round.lock().unwrap();
+                round.best_difficulty = best_difficulty;
+                round.hashrate = hashrate;
+                round.thread_hashrates = thread_hashrates.clone();
+            }
+            if let Some(dashboard) = dashboard.as_mut() {
+                dashboard.thread_hashrates = thread_hashrates;
+                dashboard.best_difficulty = best_difficulty;
+                dashboard.refresh();
+            }
+            if let Some(progress_bar) = progress_bar.as_ref() {
+                progress_bar.set_message(format!(
+                    "Mining... ({:.0} H/sec, best difficulty: {}, {} sec to cutoff)",
+                    hashrate,
+                    best_difficulty,
+                    cutoff_time.saturating_sub(timer.elapsed().as_secs())
+                ));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        };
+
+        let message = format!(
+            "Best hash: {} (difficulty: {}, thread: {})",
+            bs58::encode(best.hash).into_string(),
+            best.difficulty,
+            best.worker
+        );
+        if let Some(dashboard) = dashboard.as_mut() {
+            dashboard.best_difficulty = best.difficulty;
+        }
+        round.lock().unwrap().best_difficulty = best.difficulty;
+        match progress_bar {
+            Some(progress_bar) => progress_bar.finish_with_message(message),
+            None => log(dashboard, message),
+        }
+        Some(FoundHash {
+            challenge,
+            best,
+            hashes: pool.hashes().iter().sum::<u64>() - start_hashes,
         })
-        .collect();
+    }
+
+    /// Logs and records a submitted round once its reward is known.
+    fn finish_round(
+        &self,
+        submitted: Submitted,
+        args: &MineArgs,
+        session: &mut Session,
+        failures: &mut u64,
+        round: &SharedRoundStatus,
+        dashboard: &mut Option<Dashboard>,
+    ) {
+        if let Some(reward) = submitted.reward {
+            log(
+                dashboard,
+                format!(
+                    "Difficulty {} → {}{} ORE",
+                    reward.difficulty,
+                    if reward.estimated { "~" } else { "" },
+                    amount_u64_to_string(reward.reward)
+                ),
+            );
+        }
+        session.rounds += 1;
+        if submitted.result.is_ok() {
+            session.landed += 1;
+            session.fees += submitted.fee;
+        }
+        session.rewards += submitted.reward.map_or(0, |reward| reward.reward);
+        self.check_round(args, &submitted.result, failures);
+        let record = self.record_round(&submitted);
+        round.lock().unwrap().rounds += 1;
+        if let Some(dashboard) = dashboard.as_mut() {
+            dashboard.push_round(record);
+        }
+    }
 
-    let mut best_nonce = 0;
-    let mut best_difficulty = 0;
-    let mut best_hash = Hash::default();
-    for h in handles {
-        if let Ok((nonce, difficulty, hash)) = h.join() {
-            if difficulty > best_difficulty {
-                best_difficulty = difficulty;
-                best_nonce = nonce;
-                best_hash = hash;
+    /// Alerts when the program's minimum difficulty rose by the alert amount since the
+    /// last round.
+    fn check_difficulty(&self, args: &MineArgs, config: Config, last: &mut Option<u64>) {
+        let from = last.replace(config.min_difficulty);
+        if let (Some(from), Some(spike)) = (from, args.alert_difficulty_spike) {
+            if config.min_difficulty.saturating_sub(from).ge(&spike) {
+                self.notify(Event::DifficultySpike {
+                    from,
+                    to: config.min_difficulty,
+                });
             }
         }
     }
 
-    progress_bar.finish_with_message(format!(
-        "Best hash: {} (difficulty: {})",
-        bs58::encode(best_hash.h).into_string(),
-        best_difficulty
-    ));
+    /// Alerts on repeated failures.
+    fn check_round(&self, args: &MineArgs, result: &ClientResult<Signature>, failures: &mut u64) {
+        match result {
+            Ok(_) => *failures = 0,
+            Err(err) => {
+                *failures += 1;
+                if (*failures).eq(&args.alert_failures) {
+                    self.notify(Event::MineFailures {
+                        count: *failures,
+                        error: err.to_string(),
+                    });
+                }
+            }
+        }
+    }
 
-    Solution::new(best_hash.d, best_nonce.to_le_bytes())
-}
+    /// Appends the round to the round history.
+    fn record_round(&self, submitted: &Submitted) -> RoundRecord {
+        let Submitted {
+            proof,
+            found,
+            cutoff_time,
+            bus,
+            result,
+            reward,
+            ..
+        } = submitted;
+        let record = RoundRecord {
+            timestamp: chrono::Utc::now().timestamp(),
+            authority: proof.authority.to_string(),
+            challenge: bs58::encode(found.challenge).into_string(),
+            difficulty: found.best.difficulty,
+            nonce: found.best.nonce,
+            hashes: found.hashes,
+            cutoff: *cutoff_time,
+            bus: *bus,
+            priority_fee: self.priority_fee(),
+            signature: result.as_ref().ok().map(|sig| sig.to_string()),
+            landed: result.is_ok(),
+            error: result.as_ref().err().map(|err| err.to_string()),
+            reward: reward.map(|reward| reward.reward),
+            timing: reward.and_then(|reward| reward.timing),
+            estimated: reward.is_some_and(|reward| reward.estimated),
+        };
+        if let Err(err) = record.save() {
+            print_line(format!(
+                "{} Failed to record round in {}: {}",
+                "WARNING".bold().yellow(),
+                rounds_path().display(),
+                err
+            ));
+        }
+        record
+    }
 
     pub fn check_num_cores(&self, threads: u64) {
         // Check num threads
         let num_cores = num_cpus::get() as u64;
         if threads.gt(&num_cores) {
-            println!(
+            print_line(format!(
                 "{} Number of threads ({}) exceeds available cores ({})",
                 "WARNING".bold().yellow(),
                 threads,
                 num_cores
-            );
+            ));
         }
     }
 
@@ -174,8 +949,158 @@ async fn find_hash_par(
     }
 }
 
+pub fn resolve_threads(threads: Threads) -> u64 {
+    match threads {
+        Threads::Count(threads) => threads,
+        Threads::Auto => match Profile::load().threads.filter(|threads| threads.gt(&0)) {
+            Some(threads) => threads,
+            None => {
+                let num_cores = num_cpus::get() as u64;
+                println!(
+                    "{} No tuned thread count found, using {}. Run `ore benchmark --sweep` to tune it.",
+                    "WARNING".bold().yellow(),
+                    num_cores
+                );
+                num_cores
+            }
+        },
+    }
+}
+
+impl Miner {
+    /// Waits until mining is resumed over the control socket, returning the latest settings.
+    /// Claims requested in the meantime are sent while paused.
+    async fn wait_while_paused(
+        &self,
+        control: &SharedControlState,
+        args: &MineArgs,
+        claim_to: Pubkey,
+        next_claim: &mut Option<DateTime<Local>>,
+        shutdown: &Shutdown,
+        dashboard: &mut Option<Dashboard>,
+    ) -> Option<ControlState> {
+        let mut logged = false;
+        while !shutdown.requested() {
+            let state = control.lock().unwrap().clone();
+            if !state.paused {
+                if logged {
+                    log(dashboard, "Resumed".to_string());
+                }
+                return Some(state);
+            }
+            if !logged {
+                log(dashboard, "Paused".to_string());
+                logged = true;
+            }
+            if std::mem::take(&mut control.lock().unwrap().claim_requested) {
+                self.auto_claim(args, claim_to, next_claim, true, dashboard)
+                    .await;
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        None
+    }
+
+    /// Waits until the fee fits in the budgets, or returns `false` if it never will.
+    async fn wait_for_budget(
+        &self,
+        fee: u64,
+        args: &MineArgs,
+        shutdown: &Shutdown,
+        dashboard: &mut Option<Dashboard>,
+    ) -> bool {
+        while let Err(overspend) = self.check_budget(fee) {
+            if shutdown.requested() {
+                return false;
+            }
+            match overspend {
+                Overspend::Session => {
+                    log(
+                        dashboard,
+                        format!(
+                            "Session budget of {} SOL spent. Stopping...",
+                            args.session_budget.unwrap_or_default()
+                        ),
+                    );
+                    return false;
+                }
+                Overspend::Fee => {
+                    log(
+                        dashboard,
+                        format!(
+                            "The {} SOL fee is more than the daily budget of {} SOL. Stopping...",
+                            lamports_to_sol(fee),
+                            args.daily_budget.unwrap_or_default()
+                        ),
+                    );
+                    return false;
+                }
+                Overspend::Daily => {
+                    log(
+                        dashboard,
+                        format!(
+                            "Daily budget of {} SOL spent. Waiting until tomorrow...",
+                            args.daily_budget.unwrap_or_default()
+                        ),
+                    );
+                    wait_for_next_day(shutdown).await;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// The next submitted round whose reward lookup has finished, in submission order.
+///
+/// With `wait`, waits for the lookup rather than returning `None` while it runs.
+async fn next_attributed(
+    pending: &mut VecDeque<JoinHandle<Submitted>>,
+    wait: bool,
+) -> Option<Submitted> {
+    while pending
+        .front()
+        .is_some_and(|task| wait || task.is_finished())
+    {
+        if let Ok(submitted) = pending.pop_front()?.await {
+            return Some(submitted);
+        }
+    }
+    None
+}
+
+/// Waits until the next day in local time, when the daily budget resets.
+async fn wait_for_next_day(shutdown: &Shutdown) {
+    let today = Local::now().date_naive();
+    while !shutdown.requested() && Local::now().date_naive().eq(&today) {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+fn print_session(session: &Session, duration: Duration) {
+    println!("\n{}", "Session summary".bold());
+    println!("  Duration: {} min", duration.as_secs() / 60);
+    println!("  Rounds: {}", session.rounds);
+    println!("  Landed: {}", session.landed);
+    println!("  Earned: {} ORE", amount_u64_to_string(session.rewards));
+    println!("  Spent: {} SOL", lamports_to_sol(session.fees));
+    if session.skipped_unprofitable > 0 || session.skipped_over_budget > 0 {
+        println!(
+            "  Held back: {} unprofitable, {} over budget",
+            session.skipped_unprofitable, session.skipped_over_budget
+        );
+    }
+}
+
+/// Prints a message, or adds it to the dashboard's log when the dashboard is shown.
+fn log(dashboard: &mut Option<Dashboard>, message: String) {
+    match dashboard.as_mut() {
+        Some(dashboard) => dashboard.log(message),
+        None => println!("{}", message),
+    }
+}
+
 // TODO Pick a better strategy (avoid draining bus)
-fn find_bus() -> Pubkey {
-    let i = rand::thread_rng().gen_range(0..BUS_COUNT);
-    BUS_ADDRESSES[i]
+fn find_bus() -> usize {
+    rand::thread_rng().gen_range(0..BUS_COUNT)
 }
@@ -0,0 +1,158 @@
+use std::str::FromStr;
+
+use colored::*;
+use solana_client::nonce_utils::{
+    self,
+    nonblocking::{data_from_account, get_account_with_commitment},
+};
+use solana_program::{
+    native_token::{lamports_to_sol, sol_to_lamports},
+    nonce::{state::Data, State},
+    pubkey::Pubkey,
+    system_instruction, system_program,
+};
+use solana_sdk::signature::{Keypair, Signer};
+
+use crate::{
+    args::{NonceArgs, NonceCommand},
+    cu_limits::CU_LIMIT_NONCE,
+    send_and_confirm::ComputeBudget,
+    signer::read_signer,
+    Miner,
+};
+
+impl Miner {
+    pub async fn nonce(&self, args: NonceArgs) {
+        let signer = self.signer();
+        match args.command {
+            NonceCommand::Create(args) => {
+                let address = nonce_address(&signer.pubkey(), &args.seed);
+                if self.rpc_client.get_account(&address).await.is_ok() {
+                    println!("Nonce account {} already exists", address);
+                    return;
+                }
+                let authority = match args.authority {
+                    Some(authority) => {
+                        Pubkey::from_str(&authority).expect("Failed to parse authority address")
+                    }
+                    None => signer.pubkey(),
+                };
+                let lamports = match args.amount {
+                    Some(amount) => sol_to_lamports(amount),
+                    None => self
+                        .rpc_client
+                        .get_minimum_balance_for_rent_exemption(State::size())
+                        .await
+                        .expect("Failed to fetch rent exemption"),
+                };
+                let ixs = system_instruction::create_nonce_account_with_seed(
+                    &signer.pubkey(),
+                    &address,
+                    &signer.pubkey(),
+                    &args.seed,
+                    &authority,
+                    lamports,
+                );
+                if self
+                    .send_and_confirm(&ixs, ComputeBudget::Fixed(CU_LIMIT_NONCE), false)
+                    .await
+                    .is_ok()
+                {
+                    println!("Nonce account: {}", address);
+                }
+            }
+            NonceCommand::Show(args) => {
+                let address = match args.address {
+                    Some(address) => Pubkey::from_str(&address).expect("Failed to parse address"),
+                    None => nonce_address(&signer.pubkey(), &args.seed),
+                };
+                match self.get_nonce_data(&address).await {
+                    Ok(data) => {
+                        let balance = self.rpc_client.get_balance(&address).await.unwrap_or(0);
+                        println!("{}: {}", "Address".bold(), address);
+                        println!("{}: {}", "Authority".bold(), data.authority);
+                        println!("{}: {}", "Nonce".bold(), data.blockhash());
+                        println!(
+                            "{}: {} lamports",
+                            "Fee per signature".bold(),
+                            data.fee_calculator.lamports_per_signature
+                        );
+                        println!("{}: {} SOL", "Balance".bold(), lamports_to_sol(balance));
+                    }
+                    Err(err) => println!("{} {}: {}", "ERROR".bold().red(), address, err),
+                }
+            }
+            NonceCommand::Advance(args) => {
+                let address = match args.address {
+                    Some(address) => Pubkey::from_str(&address).expect("Failed to parse address"),
+                    None => nonce_address(&signer.pubkey(), &args.seed),
+                };
+                let authority = self.nonce_authority(&args.nonce_authority);
+                let ix = system_instruction::advance_nonce_account(&address, &authority.pubkey());
+                self.send_and_confirm_with_signers(
+                    &[ix],
+                    ComputeBudget::Fixed(CU_LIMIT_NONCE),
+                    &[&authority],
+                    false,
+                )
+                .await
+                .ok();
+            }
+            NonceCommand::Withdraw(args) => {
+                let address = match args.address {
+                    Some(address) => Pubkey::from_str(&address).expect("Failed to parse address"),
+                    None => nonce_address(&signer.pubkey(), &args.seed),
+                };
+                let to = match args.to {
+                    Some(to) => Pubkey::from_str(&to).expect("Failed to parse wallet address"),
+                    None => signer.pubkey(),
+                };
+                let lamports = match args.amount {
+                    Some(amount) => sol_to_lamports(amount),
+                    None => self
+                        .rpc_client
+                        .get_balance(&address)
+                        .await
+                        .expect("Failed to fetch nonce balance"),
+                };
+                let authority = self.nonce_authority(&args.nonce_authority);
+                let ix = system_instruction::withdraw_nonce_account(
+                    &address,
+                    &authority.pubkey(),
+                    &to,
+                    lamports,
+                );
+                self.send_and_confirm_with_signers(
+                    &[ix],
+                    ComputeBudget::Fixed(CU_LIMIT_NONCE),
+                    &[&authority],
+                    false,
+                )
+                .await
+                .ok();
+            }
+        }
+    }
+
+    /// The keypair authorized to use the nonce account, or the signer if none is given.
+    fn nonce_authority(&self, source: &Option<String>) -> Keypair {
+        match source {
+            Some(source) => read_signer(source, &self.password_file, None),
+            None => self.signer(),
+        }
+    }
+
+    pub async fn get_nonce_data(&self, address: &Pubkey) -> Result<Data, nonce_utils::Error> {
+        let account =
+            get_account_with_commitment(&self.rpc_client, address, self.rpc_client.commitment())
+                .await?;
+        data_from_account(&account)
+    }
+}
+
+/// Nonce accounts are created at an address derived from the signer and a seed,
+/// so no extra keypair needs to be kept around.
+pub fn nonce_address(base: &Pubkey, seed: &str) -> Pubkey {
+    Pubkey::create_with_seed(base, seed, &system_program::id())
+        .expect("Failed to derive nonce address")
+}
@@ -0,0 +1,343 @@
+use std::{
+    process::Command,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use colored::*;
+use serde::Serialize;
+use serde_json::{json, Value};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::native_token::{lamports_to_sol, sol_to_lamports};
+
+use crate::{args::MineArgs, tui::print_line, utils::amount_u64_to_string, Miner};
+
+/// Minimum time between two low balance alerts.
+const LOW_BALANCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Default low balance alert threshold, as a multiple of the minimum balance, so the alert
+/// comes well before transactions are refused.
+const ALERT_BALANCE_MULTIPLE: f64 = 10.0;
+
+/// Time to wait for a webhook to respond.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Something worth telling the operator of an unattended miner about.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The payer's SOL balance fell below the alert threshold.
+    LowBalance { balance: u64, threshold: u64 },
+
+    /// Mine transactions failed several times in a row.
+    MineFailures { count: u64, error: String },
+
+    /// The program's minimum difficulty rose by at least the alert amount since the
+    /// last round.
+    DifficultySpike { from: u64, to: u64 },
+
+    /// Rewards were claimed.
+    ClaimCompleted {
+        amount: u64,
+        beneficiary: String,
+        signature: String,
+        trigger: String,
+    },
+}
+
+impl Event {
+    fn name(&self) -> &'static str {
+        match self {
+            Event::LowBalance { .. } => "low_balance",
+            Event::MineFailures { .. } => "mine_failures",
+            Event::DifficultySpike { .. } => "difficulty_spike",
+            Event::ClaimCompleted { .. } => "claim_completed",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            Event::LowBalance { balance, .. } => {
+                format!("Payer balance is low: {} SOL", lamports_to_sol(*balance))
+            }
+            Event::MineFailures { count, error } => {
+                format!("{} mine transactions failed in a row: {}", count, error)
+            }
+            Event::DifficultySpike { from, to } => {
+                format!("Minimum difficulty rose from {} to {}", from, to)
+            }
+            Event::ClaimCompleted {
+                amount,
+                beneficiary,
+                ..
+            } => format!(
+                "Claimed {} ORE to {}",
+                amount_u64_to_string(*amount),
+                beneficiary
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum WebhookFormat {
+    Json,
+    Slack,
+    Discord,
+}
+
+/// Sends alerts to the webhooks and shell command configured for `ore mine`.
+pub struct Notifier {
+    authority: Pubkey,
+    webhooks: Vec<(String, WebhookFormat)>,
+    command: Option<String>,
+
+    /// Payer balance below which to alert, in lamports.
+    low_balance: u64,
+    client: reqwest::Client,
+    last_low_balance: Mutex<Option<Instant>>,
+}
+
+impl Notifier {
+    /// Builds a notifier from the mining args, or `None` if no alert is configured.
+    pub fn from_args(authority: Pubkey, args: &MineArgs, min_sol_balance: f64) -> Option<Self> {
+        let webhooks: Vec<(String, WebhookFormat)> = args
+            .webhook
+            .iter()
+            .map(|url| (url.clone(), WebhookFormat::Json))
+            .chain(
+                args.slack_webhook
+                    .iter()
+                    .map(|url| (url.clone(), WebhookFormat::Slack)),
+            )
+            .chain(
+                args.discord_webhook
+                    .iter()
+                    .map(|url| (url.clone(), WebhookFormat::Discord)),
+            )
+            .collect();
+        if webhooks.is_empty() && args.notify_command.is_none() {
+            return None;
+        }
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .expect("Failed to build HTTP client");
+        Some(Self {
+            authority,
+            webhooks,
+            command: args.notify_command.clone(),
+            low_balance: sol_to_lamports(
+                args.alert_balance
+                    .unwrap_or(min_sol_balance * ALERT_BALANCE_MULTIPLE),
+            ),
+            client,
+            last_low_balance: Mutex::new(None),
+        })
+    }
+
+    /// Sends the event in the background, so a slow webhook can't hold up mining.
+    pub fn notify(&self, event: Event) {
+        // Low balance is checked before every transaction, so only alert once in a while
+        if let Event::LowBalance { .. } = event {
+            let mut last = self.last_low_balance.lock().unwrap();
+            if last.is_some_and(|last| last.elapsed().lt(&LOW_BALANCE_INTERVAL)) {
+                return;
+            }
+            *last = Some(Instant::now());
+        }
+
+        let message = event.message();
+        let mut payload = serde_json::to_value(&event).unwrap_or_else(|_| json!({}));
+        payload["message"] = Value::String(message.clone());
+        payload["authority"] = Value::String(self.authority.to_string());
+        payload["timestamp"] = json!(chrono::Utc::now().timestamp());
+
+        for (url, format) in self.webhooks.iter() {
+            let body = match format {
+                WebhookFormat::Json => payload.clone(),
+                WebhookFormat::Slack => json!({ "text": format!("ORE miner: {}", message) }),
+                WebhookFormat::Discord => {
+                    json!({ "content": format!("ORE miner: {}", message) })
+                }
+            };
+            let request = self.client.post(url).json(&body);
+            let url = url.clone();
+            tokio::spawn(async move {
+                let result = request
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status());
+                if let Err(err) = result {
+                    print_line(format!(
+                        "{} Failed to send alert to {}: {}",
+                        "WARNING".bold().yellow(),
+                        url,
+                        err
+                    ));
+                }
+            });
+        }
+
+        if let Some(command) = self.command.clone() {
+            let event_name = event.name();
+            let json = payload.to_string();
+            tokio::task::spawn_blocking(move || {
+                let result = Command::new("sh")
+                    .arg("-c")
+                    .arg(&command)
+                    .env("ORE_EVENT", event_name)
+                    .env("ORE_MESSAGE", message)
+                    .env("ORE_EVENT_JSON", json)
+                    .status();
+                match result {
+                    Ok(status) if !status.success() => print_line(format!(
+                        "{} Alert command exited with {}",
+                        "WARNING".bold().yellow(),
+                        status
+                    )),
+                    Err(err) => print_line(format!(
+                        "{} Failed to run alert command: {}",
+                        "WARNING".bold().yellow(),
+                        err
+                    )),
+                    _ => {}
+                }
+            });
+        }
+    }
+}
+
+impl Miner {
+    /// Sends an alert, if `ore mine` was started with any configured.
+    pub fn notify(&self, event: Event) {
+        if let Some(notifier) = self.notifier.get() {
+            notifier.notify(event);
+        }
+    }
+
+    /// Alerts if the payer balance is below the alert threshold.
+    pub fn check_balance(&self, balance: u64) {
+        if let Some(notifier) = self.notifier.get() {
+            if balance < notifier.low_balance {
+                notifier.notify(Event::LowBalance {
+                    balance,
+                    threshold: notifier.low_balance,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    use super::*;
+
+    /// Accepts `count` requests, answering 200, and returns their bodies by path.
+    fn listen(count: usize) -> (String, mpsc::Receiver<(String, Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().take(count) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let path = line.split_whitespace().nth(1).unwrap().to_string();
+                let mut length = 0;
+                loop {
+                    line.clear();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                    .unwrap();
+                tx.send((path, serde_json::from_slice(&body).unwrap()))
+                    .unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn posts_each_webhook_format() {
+        let (url, rx) = listen(3);
+        let authority = Pubkey::new_unique();
+        let notifier = Notifier {
+            authority,
+            webhooks: vec![
+                (format!("{}/json", url), WebhookFormat::Json),
+                (format!("{}/slack", url), WebhookFormat::Slack),
+                (format!("{}/discord", url), WebhookFormat::Discord),
+            ],
+            command: None,
+            low_balance: 0,
+            client: reqwest::Client::new(),
+            last_low_balance: Mutex::new(None),
+        };
+        notifier.notify(Event::MineFailures {
+            count: 3,
+            error: "Max retries".to_string(),
+        });
+
+        let bodies: HashMap<String, Value> = tokio::task::spawn_blocking(move || {
+            (0..3)
+                .map(|_| rx.recv_timeout(Duration::from_secs(10)).unwrap())
+                .collect()
+        })
+        .await
+        .unwrap();
+        let message = "3 mine transactions failed in a row: Max retries";
+        let json = &bodies["/json"];
+        assert_eq!(json["event"], "mine_failures");
+        assert_eq!(json["count"], 3);
+        assert_eq!(json["error"], "Max retries");
+        assert_eq!(json["message"], message);
+        assert_eq!(json["authority"], authority.to_string());
+        assert!(json["timestamp"].is_i64());
+        assert_eq!(
+            bodies["/slack"],
+            json!({ "text": format!("ORE miner: {}", message) })
+        );
+        assert_eq!(
+            bodies["/discord"],
+            json!({ "content": format!("ORE miner: {}", message) })
+        );
+    }
+
+    #[test]
+    fn alert_balance_defaults_to_a_multiple_of_the_minimum() {
+        let args = |extra: &[&str]| {
+            let mut argv = vec!["mine", "--webhook", "http://localhost"];
+            argv.extend_from_slice(extra);
+            <MineArgs as clap::Parser>::parse_from(argv)
+        };
+        let notifier = Notifier::from_args(Pubkey::new_unique(), &args(&[]), 0.01).unwrap();
+        assert_eq!(notifier.low_balance, sol_to_lamports(0.1));
+        let notifier = Notifier::from_args(
+            Pubkey::new_unique(),
+            &args(&["--alert-balance", "0.2"]),
+            0.01,
+        )
+        .unwrap();
+        assert_eq!(notifier.low_balance, sol_to_lamports(0.2));
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+use drillx::equix;
+
+use crate::affinity::WorkerConfig;
+
+/// A challenge for the workers to hash.
+#[derive(Clone, Copy, Debug)]
+struct Job {
+    id: u64,
+    challenge: [u8; 32],
+    min_difficulty: u32,
+
+    /// Keep hashing from where the previous job left off, if it had the same challenge.
+    resume: bool,
+}
+
+/// A hash which beat the best difficulty its worker had found for a job.
+#[derive(Clone, Copy, Debug)]
+pub struct WorkerResult {
+    pub job: u64,
+    pub worker: u64,
+    pub nonce: u64,
+    pub difficulty: u32,
+    pub digest: [u8; 16],
+    pub hash: [u8; 32],
+}
+
+/// Long-lived mining threads which keep their solver memory between rounds.
+///
+/// Each call to `start` sends a new challenge to every worker, which drops whatever
+/// it was hashing and switches to it. Workers go idle once a job is solved, and exit
+/// when the pool is dropped.
+pub struct WorkerPool {
+    jobs: Vec<Sender<Job>>,
+    results: Receiver<WorkerResult>,
+    solved: Arc<AtomicU64>,
+    hashes: Arc<Vec<AtomicU64>>,
+    job: Option<Job>,
+    best: Option<WorkerResult>,
+
+    /// Set when the current job was started ahead of its round by `prefetch`.
+    prefetched: bool,
+}
+
+impl WorkerPool {
+    pub fn new(threads: u64, worker_config: WorkerConfig) -> Self {
+        // Without a worker, no job would ever be solved
+        let threads = threads.max(1);
+        let (result_tx, results) = mpsc::channel();
+        let solved = Arc::new(AtomicU64::new(0));
+        let hashes = Arc::new((0..threads).map(|_| AtomicU64::new(0)).collect::<Vec<_>>());
+        let jobs = (0..threads)
+            .map(|i| {
+                let (job_tx, job_rx) = mpsc::channel();
+                let result_tx = result_tx.clone();
+                let solved = solved.clone();
+                let hashes = hashes.clone();
+                let worker_config = worker_config.clone();
+                thread::spawn(move || {
+                    run_worker(i, threads, job_rx, result_tx, solved, hashes, worker_config)
+                });
+                job_tx
+            })
+            .collect();
+        Self {
+            jobs,
+            results,
+            solved,
+            hashes,
+            job: None,
+            best: None,
+            prefetched: false,
+        }
+    }
+
+    /// Starts hashing a new challenge, abandoning the current one.
+    ///
+    /// Raising the difficulty of the current challenge resumes where the workers left off,
+    /// rather than hashing the same nonces again.
+    pub fn start(&mut self, challenge: [u8; 32], min_difficulty: u32) {
+        // Carry on with a challenge which was prefetched for this round
+        if std::mem::take(&mut self.prefetched)
+            && self.job.is_some_and(|job| {
+                job.challenge.eq(&challenge) && job.min_difficulty.eq(&min_difficulty)
+            })
+        {
+            return;
+        }
+        let resume = self.job.is_some_and(|job| {
+            job.challenge.eq(&challenge) && job.min_difficulty.lt(&min_difficulty)
+        });
+        self.send(challenge, min_difficulty, resume);
+    }
+
+    /// Carries on hashing the current challenge from where the workers left off, whatever
+    /// the difficulty, so a round held back without submitting doesn't hash the same nonces
+    /// again. Starts over if the challenge has changed.
+    pub fn resume(&mut self, challenge: [u8; 32], min_difficulty: u32) {
+        self.prefetched = false;
+        let resume = self.job.is_some_and(|job| job.challenge.eq(&challenge));
+        self.send(challenge, min_difficulty, resume);
+    }
+
+    fn send(&mut self, challenge: [u8; 32], min_difficulty: u32, resume: bool) {
+        let job = Job {
+            id: self.job.map_or(1, |job| job.id + 1),
+            challenge,
+            min_difficulty,
+            resume,
+        };
+        for jobs in self.jobs.iter() {
+            jobs.send(job).ok();
+        }
+        self.job = Some(job);
+        self.best = None;
+    }
+
+    /// Starts hashing the next round's challenge as soon as it is known, so the round's
+    /// `start` carries on from it rather than starting over.
+    pub fn prefetch(&mut self, challenge: [u8; 32], min_difficulty: u32) {
+        self.start(challenge, min_difficulty);
+        self.prefetched = true;
+    }
+
+    /// Stops hashing the current job, keeping the best hash found so far.
+    pub fn stop(&mut self) {
+        if let Some(job) = self.job {
+            self.solved.fetch_max(job.id, Ordering::Relaxed);
+        }
+        self.poll();
+    }
+
+    /// Collects worker results and returns the best one if it solves the current job.
+    pub fn poll(&mut self) -> Option<WorkerResult> {
+        let job = self.job?;
+        while let Ok(result) = self.results.try_recv() {
+            if result.job.ne(&job.id) {
+                continue;
+            }
+            match self.best {
+                Some(best) if best.difficulty >= result.difficulty => {}
+                _ => self.best = Some(result),
+            }
+        }
+        self.best
+            .filter(|best| best.difficulty > job.min_difficulty)
+    }
+
+    /// The best hash found for the current job so far.
+    pub fn best(&self) -> Option<WorkerResult> {
+        self.best
+    }
+
+    /// The number of hashes computed by each worker since the pool started.
+    pub fn hashes(&self) -> Vec<u64> {
+        self.hashes
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect()
+    }
+}
+
+fn run_worker(
+    i: u64,
+    threads: u64,
+    jobs: Receiver<Job>,
+    results: Sender<WorkerResult>,
+    solved: Arc<AtomicU64>,
+    hashes: Arc<Vec<AtomicU64>>,
+    worker_config: WorkerConfig,
+) {
+    // Pin and deprioritize before allocating, so memory lands near the core
+    worker_config.apply(i);
+    let mut memory = equix::SolverMemory::new();
+    let first_nonce = u64::MAX.saturating_div(threads).saturating_mul(i);
+    let Ok(mut job) = jobs.recv() else {
+        return;
+    };
+    let mut nonce = first_nonce;
+    let mut best_difficulty = 0;
+    loop {
+        // Switch to a newer challenge, if one was sent, or wait for one once this is solved
+        let next = match jobs.try_recv() {
+            Ok(next) => Some(next),
+            Err(TryRecvError::Disconnected) => return,
+            Err(TryRecvError::Empty) if solved.load(Ordering::Relaxed) >= job.id => {
+                match jobs.recv() {
+                    Ok(next) => Some(next),
+                    Err(_) => return,
+                }
+            }
+            Err(TryRecvError::Empty) => None,
+        };
+        if let Some(next) = next {
+            if !next.resume || next.challenge.ne(&job.challenge) {
+                nonce = first_nonce;
+                best_difficulty = 0;
+            }
+            job = next;
+            continue;
+        }
+
+        if let Ok(hx) = drillx::hash_with_memory(&mut memory, &job.challenge, &nonce.to_le_bytes())
+        {
+            let difficulty = hx.difficulty();
+            if difficulty > best_difficulty {
+                best_difficulty = difficulty;
+                if difficulty > job.min_difficulty {
+                    solved.fetch_max(job.id, Ordering::Relaxed);
+                }
+                let result = WorkerResult {
+                    job: job.id,
+                    worker: i,
+                    nonce,
+                    difficulty,
+                    digest: hx.d,
+                    hash: hx.h,
+                };
+                if results.send(result).is_err() {
+                    return;
+                }
+            }
+        }
+        hashes[i as usize].fetch_add(1, Ordering::Relaxed);
+        nonce += 1;
+    }
+}
@@ -0,0 +1,33 @@
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::ore_dir;
+
+/// Machine-specific settings persisted between runs.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Profile {
+    /// Thread count recommended by `ore benchmark --sweep`.
+    pub threads: Option<u64>,
+}
+
+impl Profile {
+    pub fn load() -> Self {
+        fs::read_to_string(profile_path())
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let path = profile_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+pub fn profile_path() -> PathBuf {
+    ore_dir().join("profile.json")
+}
@@ -0,0 +1,205 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, Local, Timelike};
+
+/// Furthest ahead to look for the next run, in minutes.
+const MAX_LOOKAHEAD: i64 = 366 * 24 * 60;
+
+/// A cron-like schedule of `minute hour day-of-month month day-of-week` fields, in local time.
+///
+/// Each field accepts `*`, numbers, ranges (`1-5`) and steps (`*/15`, `0-30/10`),
+/// separated by commas. Days of the week count from Sunday as 0 (or 7).
+///
+/// As in cron, when both day fields are restricted a day matching either one matches. A day
+/// field starting with `*`, like `*/2`, counts as unrestricted.
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+
+    /// Whether the day fields start with `*`.
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    /// The first minute after `time` matching the schedule.
+    pub fn next_after(&self, time: DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut time = time.with_second(0)?.with_nanosecond(0)?;
+        for _ in 0..MAX_LOOKAHEAD {
+            time += Duration::minutes(1);
+            if self.matches(&time) {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    fn matches(&self, time: &DateTime<Local>) -> bool {
+        let day = self.days[time.day() as usize];
+        let weekday = self.weekdays[time.weekday().num_days_from_sunday() as usize];
+
+        // As in cron, a day matches either field when both are restricted, and both otherwise
+        let day_matches = if self.any_day || self.any_weekday {
+            day && weekday
+        } else {
+            day || weekday
+        };
+        self.minutes[time.minute() as usize]
+            && self.hours[time.hour() as usize]
+            && self.months[time.month() as usize]
+            && day_matches
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        };
+        let mut weekdays_set = parse_field(weekdays, 0, 7)?;
+
+        // Sunday can be written as 0 or 7
+        if weekdays_set[7] {
+            weekdays_set[0] = true;
+        }
+        Ok(Schedule {
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays: weekdays_set,
+            any_day: days.starts_with('*'),
+            any_weekday: weekdays.starts_with('*'),
+        })
+    }
+}
+
+/// Parses one schedule field into a lookup table indexed by value.
+fn parse_field(field: &str, min: usize, max: usize) -> Result<Vec<bool>, String> {
+    let parse = |n: &str| {
+        n.parse::<usize>()
+            .ok()
+            .filter(|n| (min..=max).contains(n))
+            .ok_or_else(|| format!("invalid value {} (expected {}-{})", n, min, max))
+    };
+    let mut set = vec![false; max + 1];
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => match step.parse::<usize>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("invalid step {}", step)),
+            },
+            None => (item, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (parse(start)?, parse(end)?),
+                // With a step, a single value starts a range to the maximum
+                None if item.contains('/') => (parse(range)?, max),
+                None => (parse(range)?, parse(range)?),
+            },
+        };
+        if start > end {
+            return Err(format!("invalid range {}", range));
+        }
+        for i in (start..=end).step_by(step) {
+            set[i] = true;
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn values(set: Vec<bool>) -> Vec<usize> {
+        set.iter()
+            .enumerate()
+            .filter(|(_, &on)| on)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2024, 6, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_field_values() {
+        assert_eq!(
+            values(parse_field("*", 1, 12).unwrap()),
+            (1..=12).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            values(parse_field("1-5", 0, 59).unwrap()),
+            vec![1, 2, 3, 4, 5]
+        );
+        assert_eq!(
+            values(parse_field("*/15", 0, 59).unwrap()),
+            vec![0, 15, 30, 45]
+        );
+        assert_eq!(
+            values(parse_field("0-30/10", 0, 59).unwrap()),
+            vec![0, 10, 20, 30]
+        );
+        assert_eq!(values(parse_field("5/20", 0, 59).unwrap()), vec![5, 25, 45]);
+        assert_eq!(values(parse_field("1,3,7", 0, 7).unwrap()), vec![1, 3, 7]);
+    }
+
+    #[test]
+    fn parse_field_errors() {
+        assert!(parse_field("60", 0, 59).is_err());
+        assert!(parse_field("0", 1, 31).is_err());
+        assert!(parse_field("5-1", 0, 59).is_err());
+        assert!(parse_field("*/0", 0, 59).is_err());
+        assert!(parse_field("a", 0, 59).is_err());
+        assert!(parse_field("", 0, 59).is_err());
+    }
+
+    #[test]
+    fn next_after_daily() {
+        let schedule = Schedule::from_str("30 9 * * *").unwrap();
+        assert_eq!(schedule.next_after(at(3, 9, 0)), Some(at(3, 9, 30)));
+        assert_eq!(schedule.next_after(at(3, 9, 30)), Some(at(4, 9, 30)));
+        assert_eq!(schedule.next_after(at(3, 10, 0)), Some(at(4, 9, 30)));
+    }
+
+    #[test]
+    fn next_after_days() {
+        // 2024-06-03 is a Monday; Sunday can be written as 7
+        let sunday = Schedule::from_str("0 12 * * 7").unwrap();
+        assert_eq!(sunday.next_after(at(3, 0, 0)), Some(at(9, 12, 0)));
+
+        // Either day field matches when both are restricted
+        let either = Schedule::from_str("0 0 15 * 1").unwrap();
+        assert_eq!(either.next_after(at(4, 0, 0)), Some(at(10, 0, 0)));
+        assert_eq!(either.next_after(at(11, 0, 0)), Some(at(15, 0, 0)));
+
+        // A day field starting with * still has to match, as in cron
+        let odd_mondays = Schedule::from_str("0 0 */2 * 1").unwrap();
+        assert_eq!(odd_mondays.next_after(at(4, 0, 0)), Some(at(17, 0, 0)));
+    }
+
+    #[test]
+    fn from_str_errors() {
+        assert!(Schedule::from_str("0 0 * *").is_err());
+        assert!(Schedule::from_str("0 24 * * *").is_err());
+        assert!(Schedule::from_str("0 0 * 13 *").is_err());
+    }
+}
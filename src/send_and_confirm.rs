@@ -1,26 +1,29 @@
-use std::time::Duration;
+use std::{str::FromStr, time::Duration};
 
+use base64::{prelude::BASE64_STANDARD, Engine};
 use colored::*;
 use solana_client::{
     client_error::{ClientError, ClientErrorKind, Result as ClientResult},
     rpc_config::RpcSendTransactionConfig,
 };
-use solana_program::{
-    instruction::Instruction,
-    native_token::{lamports_to_sol, sol_to_lamports},
-};
-use solana_rpc_client::spinner;
+use solana_program::{instruction::Instruction, pubkey::Pubkey, system_instruction};
 use solana_sdk::{
     commitment_config::CommitmentLevel,
     compute_budget::ComputeBudgetInstruction,
-    signature::{Signature, Signer},
-    transaction::Transaction,
+    hash::Hash,
+    message::{v0, VersionedMessage},
+    packet::PACKET_DATA_SIZE,
+    signature::{Keypair, Signature, Signer},
+    transaction::{Transaction, VersionedTransaction},
 };
 use solana_transaction_status::{TransactionConfirmationStatus, UiTransactionEncoding};
 
-use crate::Miner;
-
-const MIN_SOL_BALANCE: f64 = 0.005;
+use crate::{
+    args::{SignArgs, TxEncoding},
+    signer::read_signer,
+    tui::{self, finish_progress, print_line},
+    Miner,
+};
 
 const RPC_RETRIES: usize = 0;
 const _SIMULATION_RETRIES: usize = 4;
@@ -42,24 +45,151 @@ impl Miner {
         compute_budget: ComputeBudget,
         skip_confirm: bool,
     ) -> ClientResult<Signature> {
-        let progress_bar = spinner::new_progress_bar();
+        self.send_and_confirm_with_signers(ixs, compute_budget, &[], skip_confirm)
+            .await
+    }
+
+    /// Sends the transaction like `send_and_confirm`, also signed by other keypairs, such as
+    /// a nonce authority.
+    pub async fn send_and_confirm_with_signers(
+        &self,
+        ixs: &[Instruction],
+        compute_budget: ComputeBudget,
+        co_signers: &[&Keypair],
+        skip_confirm: bool,
+    ) -> ClientResult<Signature> {
         let signer = self.signer();
         let client = self.rpc_client.clone();
 
-        // Return error, if balance is zero
+        // Make sure the payer can afford the transaction
         if let Ok(balance) = client.get_balance(&signer.pubkey()).await {
-            if balance <= sol_to_lamports(MIN_SOL_BALANCE) {
-                panic!(
-                    "{} Insufficient balance: {} SOL\nPlease top up with at least {} SOL",
-                    "ERROR".bold().red(),
-                    lamports_to_sol(balance),
-                    MIN_SOL_BALANCE
-                );
+            self.check_balance(balance);
+            self.ensure_funded(signer.pubkey(), balance).await;
+        }
+
+        // Sign tx
+        let final_ixs = self.build_instructions(ixs, compute_budget, None);
+        let (hash, _slot) = client
+            .get_latest_blockhash_with_commitment(self.rpc_client.commitment())
+            .await
+            .unwrap();
+        let mut signers = vec![&signer];
+        for co_signer in co_signers {
+            if co_signer.pubkey().ne(&signer.pubkey()) {
+                signers.push(co_signer);
             }
         }
+        let tx = self.sign_transaction(&final_ixs, &signers, hash).await;
 
-        // Set compute units
+        // Submit tx
+        self.submit_and_confirm(&tx, skip_confirm).await
+    }
+
+    /// Sends the transaction, or only signs and prints it if `--sign-only` is set.
+    pub async fn send_or_sign(
+        &self,
+        ixs: &[Instruction],
+        compute_budget: ComputeBudget,
+        args: &SignArgs,
+    ) -> ClientResult<Signature> {
+        if !args.sign_only && args.blockhash.is_none() && args.nonce.is_none() {
+            return self.send_and_confirm(ixs, compute_budget, false).await;
+        }
+
+        // Make sure the payer can afford the transaction, unless it is only signed
+        let signer = self.signer();
+        if !args.sign_only {
+            if let Ok(balance) = self.rpc_client.get_balance(&signer.pubkey()).await {
+                self.check_balance(balance);
+                self.ensure_funded(signer.pubkey(), balance).await;
+            }
+        }
+
+        // Advance the durable nonce first, if one is used. With --sign-only, the nonce
+        // authority can be an address, to sign elsewhere.
+        let absent_authority = args
+            .nonce_authority
+            .as_ref()
+            .filter(|_| args.sign_only)
+            .and_then(|source| Pubkey::from_str(source).ok());
+        let nonce_authority = args
+            .nonce_authority
+            .as_ref()
+            .filter(|_| absent_authority.is_none())
+            .map(|source| read_signer(source, &self.password_file, None));
+        let nonce = args
+            .nonce
+            .as_ref()
+            .map(|nonce| Pubkey::from_str(nonce).expect("Failed to parse nonce address"));
+        let advance_nonce = nonce.map(|nonce| {
+            let authority = absent_authority
+                .unwrap_or_else(|| nonce_authority.as_ref().unwrap_or(&signer).pubkey());
+            system_instruction::advance_nonce_account(&nonce, &authority)
+        });
+
+        // Use the given blockhash, so the tx can be signed without network access
+        let hash = match (&args.blockhash, nonce) {
+            (Some(hash), _) => Hash::from_str(hash).expect("Failed to parse blockhash"),
+            (None, Some(nonce)) => match self.get_nonce_data(&nonce).await {
+                Ok(data) => data.blockhash(),
+                Err(err) => {
+                    return Err(ClientError {
+                        request: None,
+                        kind: ClientErrorKind::Custom(err.to_string()),
+                    })
+                }
+            },
+            (None, None) => {
+                self.rpc_client
+                    .get_latest_blockhash_with_commitment(self.rpc_client.commitment())
+                    .await?
+                    .0
+            }
+        };
+        let final_ixs = self.build_instructions(ixs, compute_budget, advance_nonce);
+        let mut signers = vec![&signer];
+        if let Some(nonce_authority) = &nonce_authority {
+            if nonce_authority.pubkey().ne(&signer.pubkey()) {
+                signers.push(nonce_authority);
+            }
+        }
+        let tx = self.sign_transaction(&final_ixs, &signers, hash).await;
+
+        // Print the tx for a later `ore broadcast`
+        if args.sign_only {
+            let data = bincode::serialize(&tx).expect("Failed to serialize transaction");
+            println!("Blockhash: {}", hash);
+            println!("Signature: {}", tx.signatures[0]);
+            let missing = missing_signers(&tx);
+            if !missing.is_empty() {
+                println!("Missing signers (add with ore broadcast --signer PUBKEY=SIGNATURE):");
+                for pubkey in missing {
+                    println!("  {}", pubkey);
+                }
+            }
+            println!(
+                "Transaction: {}",
+                match args.encoding {
+                    TxEncoding::Base58 => bs58::encode(data).into_string(),
+                    TxEncoding::Base64 => BASE64_STANDARD.encode(data),
+                }
+            );
+            return Ok(tx.signatures[0]);
+        }
+        self.submit_and_confirm(&tx, false).await
+    }
+
+    pub fn build_instructions(
+        &self,
+        ixs: &[Instruction],
+        compute_budget: ComputeBudget,
+        advance_nonce: Option<Instruction>,
+    ) -> Vec<Instruction> {
+        // The nonce advance must be the first instruction
         let mut final_ixs = vec![];
+        final_ixs.extend(advance_nonce);
+
+        // Set compute units
         match compute_budget {
             ComputeBudget::Dynamic => {
                 // TODO simulate
@@ -70,11 +200,67 @@ impl Miner {
             }
         }
         final_ixs.push(ComputeBudgetInstruction::set_compute_unit_price(
-            self.priority_fee,
+            self.priority_fee(),
         ));
         final_ixs.extend_from_slice(ixs);
+        final_ixs
+    }
+
+    /// Signs a legacy transaction, or a v0 transaction using the configured
+    /// lookup table if the legacy one would exceed the packet size limit.
+    ///
+    /// Signers the transaction needs but which aren't given are left for a later
+    /// `ore broadcast --signer`. See `missing_signers`.
+    pub async fn sign_transaction(
+        &self,
+        ixs: &[Instruction],
+        signers: &[&Keypair],
+        hash: Hash,
+    ) -> VersionedTransaction {
+        let payer = signers[0].pubkey();
+        let mut tx = Transaction::new_with_payer(ixs, Some(&payer));
+        tx.try_partial_sign(signers, hash)
+            .expect("Failed to sign transaction");
+        let size = bincode::serialized_size(&tx).unwrap_or(u64::MAX) as usize;
+        if size <= PACKET_DATA_SIZE {
+            return tx.into();
+        }
+        if self.lookup_table.is_none() {
+            print_line(format!(
+                "{} Transaction is {} bytes (max {}). Pass --lookup-table to compress it.",
+                "WARNING".bold().yellow(),
+                size,
+                PACKET_DATA_SIZE
+            ));
+            return tx.into();
+        }
+        let Some(lookup_table) = self.load_lookup_table().await else {
+            return tx.into();
+        };
+        let message = v0::Message::try_compile(&payer, ixs, &[lookup_table], hash)
+            .expect("Failed to compile v0 message");
+        let mut tx = VersionedTransaction {
+            signatures: vec![Signature::default(); message.header.num_required_signatures as usize],
+            message: VersionedMessage::V0(message),
+        };
+        let data = tx.message.serialize();
+        for signer in signers {
+            add_signature(&mut tx, signer.pubkey(), signer.sign_message(&data));
+        }
+        tx
+    }
 
-        // Build tx
+    /// Submits a signed transaction and waits for it to be confirmed.
+    ///
+    /// Records the fee of a transaction that lands, even if it fails, or of one sent without
+    /// waiting for confirmation, as it will most likely land.
+    pub async fn submit_and_confirm(
+        &self,
+        tx: &VersionedTransaction,
+        skip_confirm: bool,
+    ) -> ClientResult<Signature> {
+        let progress_bar = tui::new_progress_bar();
+        let client = self.rpc_client.clone();
         let send_cfg = RpcSendTransactionConfig {
             skip_preflight: true,
             preflight_commitment: Some(CommitmentLevel::Confirmed),
@@ -82,24 +268,17 @@ impl Miner {
             max_retries: Some(RPC_RETRIES),
             min_context_slot: None,
         };
-        let mut tx = Transaction::new_with_payer(&final_ixs, Some(&signer.pubkey()));
-
-        // Sign tx
-        let (hash, _slot) = client
-            .get_latest_blockhash_with_commitment(self.rpc_client.commitment())
-            .await
-            .unwrap();
-        tx.sign(&[&signer], hash);
 
         // Submit tx
         let mut attempts = 0;
         loop {
             progress_bar.set_message(format!("Submitting transaction... (attempt {})", attempts));
-            match client.send_transaction_with_config(&tx, send_cfg).await {
+            match client.send_transaction_with_config(tx, send_cfg).await {
                 Ok(sig) => {
                     // Skip confirmation
                     if skip_confirm {
-                        progress_bar.finish_with_message(format!("Sent: {}", sig));
+                        self.record_fee(tx);
+                        finish_progress(&progress_bar, format!("Sent: {}", sig));
                         return Ok(sig);
                     }
 
@@ -108,31 +287,29 @@ impl Miner {
                         std::thread::sleep(Duration::from_millis(CONFIRM_DELAY));
                         match client.get_signature_statuses(&[sig]).await {
                             Ok(signature_statuses) => {
-                                for status in signature_statuses.value {
-                                    if let Some(status) = status {
-                                        if let Some(err) = status.err {
-                                            progress_bar.finish_with_message(format!(
-                                                "{}: {}",
-                                                "ERROR".bold().red(),
-                                                err
-                                            ));
-                                            return Err(ClientError {
-                                                request: None,
-                                                kind: ClientErrorKind::Custom(err.to_string()),
-                                            });
-                                        }
-                                        if let Some(confirmation) = status.confirmation_status {
-                                            match confirmation {
-                                                TransactionConfirmationStatus::Processed => {}
-                                                TransactionConfirmationStatus::Confirmed
-                                                | TransactionConfirmationStatus::Finalized => {
-                                                    progress_bar.finish_with_message(format!(
-                                                        "{} {}",
-                                                        "OK".bold().green(),
-                                                        sig
-                                                    ));
-                                                    return Ok(sig);
-                                                }
+                                for status in signature_statuses.value.into_iter().flatten() {
+                                    if let Some(err) = status.err {
+                                        self.record_fee(tx);
+                                        finish_progress(
+                                            &progress_bar,
+                                            format!("{}: {}", "ERROR".bold().red(), err),
+                                        );
+                                        return Err(ClientError {
+                                            request: None,
+                                            kind: ClientErrorKind::Custom(err.to_string()),
+                                        });
+                                    }
+                                    if let Some(confirmation) = status.confirmation_status {
+                                        match confirmation {
+                                            TransactionConfirmationStatus::Processed => {}
+                                            TransactionConfirmationStatus::Confirmed
+                                            | TransactionConfirmationStatus::Finalized => {
+                                                self.record_fee(tx);
+                                                finish_progress(
+                                                    &progress_bar,
+                                                    format!("{} {}", "OK".bold().green(), sig),
+                                                );
+                                                return Ok(sig);
                                             }
                                         }
                                     }
@@ -144,7 +321,7 @@ impl Miner {
                                 progress_bar.set_message(format!(
                                     "{}: {}",
                                     "ERROR".bold().red(),
-                                    err.kind().to_string()
+                                    err.kind()
                                 ));
                             }
                         }
@@ -153,11 +330,7 @@ impl Miner {
 
                 // Handle submit errors
                 Err(err) => {
-                    progress_bar.set_message(format!(
-                        "{}: {}",
-                        "ERROR".bold().red(),
-                        err.kind().to_string()
-                    ));
+                    progress_bar.set_message(format!("{}: {}", "ERROR".bold().red(), err.kind()));
                 }
             }
 
@@ -165,7 +338,10 @@ impl Miner {
             std::thread::sleep(Duration::from_millis(GATEWAY_DELAY));
             attempts += 1;
             if attempts > GATEWAY_RETRIES {
-                progress_bar.finish_with_message(format!("{}: Max retries", "ERROR".bold().red()));
+                finish_progress(
+                    &progress_bar,
+                    format!("{}: Max retries", "ERROR".bold().red()),
+                );
                 return Err(ClientError {
                     request: None,
                     kind: ClientErrorKind::Custom("Max retries".into()),
@@ -231,3 +407,53 @@ impl Miner {
         // }
     }
 }
+
+/// Signers whose signature a transaction is still missing.
+pub fn missing_signers(tx: &VersionedTransaction) -> Vec<Pubkey> {
+    tx.message
+        .static_account_keys()
+        .iter()
+        .zip(tx.signatures.iter())
+        .filter(|(_, signature)| signature.eq(&&Signature::default()))
+        .map(|(pubkey, _)| *pubkey)
+        .collect()
+}
+
+/// Puts a signer's signature in its place in the transaction, returning false if the
+/// transaction doesn't need that signer.
+pub fn add_signature(tx: &mut VersionedTransaction, pubkey: Pubkey, signature: Signature) -> bool {
+    let signers = tx.message.header().num_required_signatures as usize;
+    let position = tx.message.static_account_keys()[..signers]
+        .iter()
+        .position(|key| key.eq(&pubkey));
+    match position.and_then(|i| tx.signatures.get_mut(i)) {
+        Some(slot) => {
+            *slot = signature;
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_missing_signature() {
+        let (payer, authority) = (Keypair::new(), Keypair::new());
+        let nonce = Pubkey::new_unique();
+        let ix = system_instruction::advance_nonce_account(&nonce, &authority.pubkey());
+        let mut tx = Transaction::new_with_payer(&[ix], Some(&payer.pubkey()));
+        tx.try_partial_sign(&[&payer], Hash::new_unique()).unwrap();
+        let mut tx = VersionedTransaction::from(tx);
+        assert_eq!(missing_signers(&tx), vec![authority.pubkey()]);
+
+        // Only signers the transaction needs have a place
+        let signature = authority.sign_message(&tx.message.serialize());
+        assert!(!add_signature(&mut tx, nonce, signature));
+        assert!(add_signature(&mut tx, authority.pubkey(), signature));
+        assert!(missing_signers(&tx).is_empty());
+        assert!(tx.verify_with_results().iter().all(|verified| *verified));
+    }
+}
@@ -0,0 +1,67 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use colored::*;
+use tokio::sync::Notify;
+
+use crate::tui;
+
+/// Ctrl-C handling for `ore mine`.
+///
+/// The first Ctrl-C asks the mining loop to finish the round and exit. A second one
+/// exits immediately.
+pub struct Shutdown {
+    requested: AtomicBool,
+    notify: Notify,
+}
+
+impl Shutdown {
+    /// Starts listening for Ctrl-C. Pass `tui` if the dashboard is shown, so the terminal
+    /// can be restored on a forced exit.
+    pub fn listen(tui: bool) -> Arc<Self> {
+        let shutdown = Arc::new(Self {
+            requested: AtomicBool::new(false),
+            notify: Notify::new(),
+        });
+        let listener = shutdown.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            listener.requested.store(true, Ordering::Relaxed);
+            listener.notify.notify_waiters();
+            if tokio::signal::ctrl_c().await.is_ok() {
+                if tui {
+                    tui::restore_terminal();
+                }
+                eprintln!("{} Interrupted", "ERROR".bold().red());
+                std::process::exit(130);
+            }
+        });
+        shutdown
+    }
+
+    pub fn requested(&self) -> bool {
+        self.requested.load(Ordering::Relaxed)
+    }
+
+    /// Waits until shutdown is requested.
+    pub async fn wait(&self) {
+        let notified = self.notify.notified();
+        if self.requested() {
+            return;
+        }
+        notified.await;
+    }
+
+    /// Waits until `timeout` after shutdown is requested.
+    pub async fn deadline(&self, timeout: Duration) {
+        self.wait().await;
+        tokio::time::sleep(timeout).await;
+    }
+}
@@ -0,0 +1,142 @@
+use std::ops::Range;
+
+use bip39::{Language, Mnemonic};
+use solana_sdk::{
+    derivation_path::DerivationPath,
+    signature::{
+        generate_seed_from_seed_phrase_and_passphrase, keypair_from_seed,
+        keypair_from_seed_and_derivation_path, read_keypair, read_keypair_file, Keypair,
+    },
+};
+
+use crate::keystore::{read_password, Keystore};
+
+/// Environment variables read by `mnemonic://` for unattended runs.
+pub const MNEMONIC_ENV: &str = "ORE_MNEMONIC";
+pub const PASSPHRASE_ENV: &str = "ORE_MNEMONIC_PASSPHRASE";
+
+/// Placeholder in a derivation path replaced by `--account-index`.
+const ACCOUNT_PLACEHOLDER: &str = "N";
+const DEFAULT_PATH: &str = "m/44'/501'/N'/0'";
+
+/// Loads a keypair from a keypair source. Accepted sources are:
+///
+/// - `<path>` or `file://<path>`: a plaintext keypair file or an encrypted keystore
+/// - `stdin://`: a plaintext keypair read from stdin
+/// - `prompt://[?key=<account>/<change>|?path=<path>]`: a seed phrase typed at the prompt
+/// - `mnemonic://[?path=<path>]`: a seed phrase from `ORE_MNEMONIC`, or the prompt
+///
+/// An `N` in a derivation path is replaced by `account_index`, so one seed can back a
+/// range of wallets, e.g. `mnemonic://?path=m/44'/501'/N'/0'`.
+pub fn read_signer(
+    source: &str,
+    password_file: &Option<String>,
+    account_index: Option<u32>,
+) -> Keypair {
+    let (scheme, rest) = source.split_once("://").unwrap_or(("file", source));
+    let (location, query) = rest.split_once('?').unwrap_or((rest, ""));
+    match scheme {
+        "file" => read_keypair_path(location, password_file),
+        "stdin" => read_keypair(&mut std::io::stdin()).expect("Failed to read keypair from stdin"),
+        "prompt" | "mnemonic" => {
+            let seed = read_seed(scheme);
+            derive_keypair(&seed, scheme, query, account_index)
+        }
+        _ => panic!("Unsupported keypair source: {}", source),
+    }
+}
+
+/// Derives the keypairs for a range of account indexes from a seed phrase source.
+pub fn derive_signers(source: &str, account_indexes: Range<u32>) -> Vec<Keypair> {
+    let (scheme, rest) = source.split_once("://").unwrap_or(("file", source));
+    let (_, query) = rest.split_once('?').unwrap_or((rest, ""));
+    if !matches!(scheme, "prompt" | "mnemonic") {
+        panic!("Cannot derive accounts from keypair source: {}", source);
+    }
+    let seed = read_seed(scheme);
+    account_indexes
+        .map(|i| derive_keypair(&seed, scheme, query, Some(i)))
+        .collect()
+}
+
+fn read_seed(scheme: &str) -> Vec<u8> {
+    let (phrase, passphrase) = read_seed_phrase(scheme);
+    generate_seed_from_seed_phrase_and_passphrase(&phrase, &passphrase)
+}
+
+/// Reads the seed phrase and passphrase of a `prompt://` or `mnemonic://` source.
+pub fn read_seed_phrase(scheme: &str) -> (String, String) {
+    match (scheme, std::env::var(MNEMONIC_ENV)) {
+        ("mnemonic", Ok(phrase)) => {
+            Mnemonic::validate(&phrase, Language::English)
+                .unwrap_or_else(|err| panic!("Invalid {}: {}", MNEMONIC_ENV, err));
+            (phrase, std::env::var(PASSPHRASE_ENV).unwrap_or_default())
+        }
+        _ => (
+            prompt_seed_phrase(),
+            rpassword::prompt_password("Passphrase (empty for none): ")
+                .expect("Failed to read passphrase"),
+        ),
+    }
+}
+
+fn derive_keypair(seed: &[u8], scheme: &str, query: &str, account_index: Option<u32>) -> Keypair {
+    // Like the Solana CLI, `prompt://` without a path uses the seed directly.
+    match (scheme, derivation_path(query, account_index)) {
+        (_, Some(path)) => keypair_from_seed_and_derivation_path(seed, Some(path)),
+        ("mnemonic", None) => keypair_from_seed_and_derivation_path(
+            seed,
+            Some(parse_path(DEFAULT_PATH, account_index)),
+        ),
+        _ => keypair_from_seed(seed),
+    }
+    .expect("Failed to derive keypair")
+}
+
+fn read_keypair_path(filepath: &str, password_file: &Option<String>) -> Keypair {
+    match Keystore::read(filepath) {
+        Some(keystore) => keystore
+            .decrypt(&read_password(password_file, &keystore.pubkey))
+            .unwrap_or_else(|err| panic!("Failed to decrypt keystore {}: {}", filepath, err)),
+        None => read_keypair_file(filepath)
+            .unwrap_or_else(|_| panic!("No keypair found at {}", filepath)),
+    }
+}
+
+fn prompt_seed_phrase() -> String {
+    loop {
+        let phrase =
+            rpassword::prompt_password("Seed phrase: ").expect("Failed to read seed phrase");
+        let phrase = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+        match Mnemonic::validate(&phrase, Language::English) {
+            Ok(()) => return phrase,
+            Err(err) => println!("Invalid seed phrase: {}", err),
+        }
+    }
+}
+
+/// Parses the `key` or `path` query parameter of a keypair source, if any.
+fn derivation_path(query: &str, account_index: Option<u32>) -> Option<DerivationPath> {
+    for param in query.split('&') {
+        match param.split_once('=') {
+            Some(("key", key)) => {
+                let key = key.replace(ACCOUNT_PLACEHOLDER, &account_index.unwrap_or(0).to_string());
+                return Some(
+                    DerivationPath::from_key_str(&key)
+                        .unwrap_or_else(|err| panic!("Invalid derivation key {}: {}", key, err)),
+                );
+            }
+            Some(("path", path)) => return Some(parse_path(path, account_index)),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_path(path: &str, account_index: Option<u32>) -> DerivationPath {
+    let path = path
+        .replace("%27", "'")
+        .replace(ACCOUNT_PLACEHOLDER, &account_index.unwrap_or(0).to_string());
+    DerivationPath::from_absolute_path_str(&path)
+        .unwrap_or_else(|err| panic!("Invalid derivation path {}: {}", path, err))
+}
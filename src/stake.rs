@@ -1,15 +1,32 @@
 use std::str::FromStr;
 
+use colored::*;
+use ore_api::consts::{MINT_ADDRESS, TOKEN_DECIMALS};
+use solana_client::client_error::Result as ClientResult;
 use solana_program::pubkey::Pubkey;
-use solana_sdk::signature::Signer;
+use solana_sdk::signature::{Signature, Signer};
+use spl_associated_token_account::get_associated_token_address;
 
 use crate::{
-    args::StakeArgs, cu_limits::CU_LIMIT_CLAIM, send_and_confirm::ComputeBudget,
-    utils::amount_f64_to_u64, Miner,
+    args::StakeArgs,
+    cu_limits::CU_LIMIT_CLAIM,
+    estimate::expected_ore_per_hour,
+    send_and_confirm::ComputeBudget,
+    utils::{
+        amount_f64_to_u64, amount_u64_to_f64, get_config, stake_multiplier,
+        try_get_proof_with_authority,
+    },
+    Miner,
 };
 
 impl Miner {
     pub async fn stake(&self, args: StakeArgs) {
+        if let Some(amount) = args.simulate {
+            self.simulate_stake(amount_f64_to_u64(amount), args.hashrate)
+                .await;
+            return;
+        }
+
         // Get signer
         let signer = self.signer();
         let sender = match args.sender {
@@ -20,24 +37,76 @@ impl Miner {
             ),
         };
 
-        // Get token account
-        let Ok(Some(token_account)) = self.rpc_client.get_token_account(&sender).await else {
-            println!("Failed to fetch token account");
-            return;
-        };
-
         // Parse amount
         let amount: u64 = if let Some(amount) = args.amount {
             amount_f64_to_u64(amount)
+        } else if args.sign.sign_only {
+            println!("--amount is required with --sign-only");
+            return;
         } else {
+            // Get token account
+            let Ok(Some(token_account)) = self.rpc_client.get_token_account(&sender).await else {
+                println!("Failed to fetch token account");
+                return;
+            };
             u64::from_str(token_account.token_amount.amount.as_str())
                 .expect("Failed to parse token balance")
         };
 
         // Send tx
         let ix = ore_api::instruction::stake(signer.pubkey(), sender, amount);
-        self.send_and_confirm(&[ix], ComputeBudget::Fixed(CU_LIMIT_CLAIM), false)
+        self.send_or_sign(&[ix], ComputeBudget::Fixed(CU_LIMIT_CLAIM), &args.sign)
             .await
             .ok();
     }
+
+    /// Stakes `amount` from the wallet's ORE token account without asking.
+    pub async fn send_stake(&self, amount: u64) -> ClientResult<Signature> {
+        let signer = self.signer();
+        let sender = get_associated_token_address(&signer.pubkey(), &MINT_ADDRESS);
+        let ix = ore_api::instruction::stake(signer.pubkey(), sender, amount);
+        self.send_and_confirm(&[ix], ComputeBudget::Fixed(CU_LIMIT_CLAIM), false)
+            .await
+    }
+
+    /// Prints how staking `amount` would change the multiplier, without sending anything.
+    async fn simulate_stake(&self, amount: u64, hashrate: Option<f64>) {
+        let config = get_config(&self.rpc_client).await;
+        let stake = try_get_proof_with_authority(&self.rpc_client, self.signer().pubkey())
+            .await
+            .map_or(0, |proof| proof.balance);
+        let new_stake = stake.saturating_add(amount);
+        let multiplier = stake_multiplier(stake, config.top_balance);
+        let new_multiplier = stake_multiplier(new_stake, config.top_balance);
+        println!(
+            "{}: {} ORE -> {} ORE",
+            "Stake".bold(),
+            amount_u64_to_f64(stake),
+            amount_u64_to_f64(new_stake)
+        );
+        println!(
+            "{}: {:.4}x -> {:.4}x ({:+.2}% rewards)",
+            "Multiplier".bold(),
+            multiplier,
+            new_multiplier,
+            (new_multiplier / multiplier - 1.0) * 100.0
+        );
+        if new_stake > config.top_balance {
+            println!(
+                "{} Stake above the top balance ({} ORE) does not raise the multiplier further",
+                "NOTE".bold(),
+                amount_u64_to_f64(config.top_balance)
+            );
+        }
+        if let Some(hashrate) = hashrate {
+            println!(
+                "{}: {:.*} -> {:.*} ORE/hour",
+                "Expected rewards".bold(),
+                TOKEN_DECIMALS as usize,
+                expected_ore_per_hour(hashrate, &config, multiplier),
+                TOKEN_DECIMALS as usize,
+                expected_ore_per_hour(hashrate, &config, new_multiplier)
+            );
+        }
+    }
 }
@@ -0,0 +1,351 @@
+use std::{
+    collections::VecDeque,
+    io::Write,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use colored::*;
+use console::{truncate_str, Term};
+use indicatif::ProgressBar;
+use ore_api::{
+    consts::{BUS_ADDRESSES, MINT_ADDRESS},
+    state::{Bus, Config, Proof},
+};
+use ore_utils::AccountDeserialize;
+use solana_program::pubkey::Pubkey;
+use solana_rpc_client::spinner;
+use solana_sdk::{native_token::lamports_to_sol, signer::Signer};
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::{
+    history::{local_time, read_records, rounds_path, RoundRecord},
+    utils::{amount_u64_to_string, stake_multiplier},
+    Miner,
+};
+
+/// Number of recent rounds shown.
+const RECENT_ROUNDS: usize = 8;
+
+/// Number of log lines kept, whether or not they fit on screen.
+const LOG_LINES: usize = 100;
+
+/// Minimum time between redraws while hashing.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Number of per-thread hashrates shown on one line.
+const THREADS_PER_LINE: usize = 4;
+
+/// Escape sequences to switch to and from the terminal's alternate screen.
+const ENTER_ALTERNATE_SCREEN: &str = "\x1b[?1049h";
+const LEAVE_ALTERNATE_SCREEN: &str = "\x1b[?1049l";
+
+/// Lines printed outside the mining loop while the dashboard is shown, waiting to be added
+/// to its log. `None` while no dashboard is shown.
+static CAPTURED: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// A full-screen view of the mining loop, drawn on the terminal's alternate screen.
+///
+/// The mining loop fills in the fields as it goes and calls `render`. The screen is
+/// restored when the dashboard is dropped.
+pub struct Dashboard {
+    term: Term,
+    authority: Pubkey,
+    pub challenge: Option<[u8; 32]>,
+    pub cutoff: Option<Instant>,
+    pub best_difficulty: u32,
+    pub thread_hashrates: Vec<f64>,
+    pub sol_balance: u64,
+    pub ore_balance: Option<String>,
+    pub stake: u64,
+    pub multiplier: f64,
+    pub busses: Vec<u64>,
+    rounds: VecDeque<RoundRecord>,
+    log: VecDeque<String>,
+    last_render: Option<Instant>,
+}
+
+impl Dashboard {
+    pub fn new(authority: Pubkey) -> Self {
+        // Start with the wallet's most recent rounds from the history
+        let mut rounds: VecDeque<RoundRecord> = read_records(&rounds_path())
+            .into_iter()
+            .filter(|round: &RoundRecord| round.authority.eq(&authority.to_string()))
+            .collect();
+        while rounds.len() > RECENT_ROUNDS {
+            rounds.pop_front();
+        }
+        let term = Term::stdout();
+        term.write_str(ENTER_ALTERNATE_SCREEN).ok();
+        term.hide_cursor().ok();
+        *CAPTURED.lock().unwrap() = Some(vec![]);
+        Self {
+            term,
+            authority,
+            challenge: None,
+            cutoff: None,
+            best_difficulty: 0,
+            thread_hashrates: vec![],
+            sol_balance: 0,
+            ore_balance: None,
+            stake: 0,
+            multiplier: 1.0,
+            busses: vec![],
+            rounds,
+            log: VecDeque::new(),
+            last_render: None,
+        }
+    }
+
+    /// Adds a line to the log pane.
+    pub fn log(&mut self, message: String) {
+        self.push_log(message);
+        self.render();
+    }
+
+    fn push_log(&mut self, message: String) {
+        self.log.push_back(message);
+        if self.log.len() > LOG_LINES {
+            self.log.pop_front();
+        }
+    }
+
+    /// Moves lines printed with `print_line` into the log pane. Returns whether there were any.
+    fn take_captured(&mut self) -> bool {
+        let lines = CAPTURED
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default();
+        let captured = !lines.is_empty();
+        for line in lines {
+            self.push_log(line);
+        }
+        captured
+    }
+
+    /// Adds a finished round to the recent rounds table.
+    pub fn push_round(&mut self, round: RoundRecord) {
+        self.rounds.push_back(round);
+        if self.rounds.len() > RECENT_ROUNDS {
+            self.rounds.pop_front();
+        }
+        self.render();
+    }
+
+    /// Redraws the dashboard, unless it was redrawn very recently.
+    pub fn refresh(&mut self) {
+        if !self.take_captured()
+            && self
+                .last_render
+                .is_some_and(|last_render| last_render.elapsed().lt(&REFRESH_INTERVAL))
+        {
+            return;
+        }
+        self.render();
+    }
+
+    /// Redraws the dashboard.
+    pub fn render(&mut self) {
+        self.take_captured();
+        self.last_render = Some(Instant::now());
+        let (rows, cols) = self.term.size();
+        let rows = rows as usize;
+        let mut lines = self.status_lines();
+        let log_rows = rows.saturating_sub(lines.len() + 1);
+        lines.push("Log".bold().to_string());
+        let skip = self.log.len().saturating_sub(log_rows);
+        lines.extend(self.log.iter().skip(skip).map(|line| format!("  {}", line)));
+        lines.truncate(rows);
+
+        // Overwrite in place rather than clearing, so the screen doesn't flicker
+        let mut frame = "\x1b[H".to_string();
+        for line in lines.iter() {
+            frame.push_str(&truncate_str(line, cols as usize, ""));
+            frame.push_str("\x1b[K\n");
+        }
+        frame.push_str("\x1b[J");
+        let mut term = &self.term;
+        term.write_all(frame.as_bytes()).ok();
+        term.flush().ok();
+    }
+
+    fn status_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("{} {}", "ORE miner".bold(), self.authority),
+            String::new(),
+            "Wallet".bold().to_string(),
+            format!(
+                "  SOL {:.9}   ORE {}   Stake {} ORE ({:.4}x multiplier)",
+                lamports_to_sol(self.sol_balance),
+                self.ore_balance.as_deref().unwrap_or("-"),
+                amount_u64_to_string(self.stake),
+                self.multiplier
+            ),
+            String::new(),
+            "Round".bold().to_string(),
+            format!(
+                "  Challenge {}",
+                self.challenge
+                    .map_or("-".to_string(), |challenge| bs58::encode(challenge)
+                        .into_string())
+            ),
+            format!(
+                "  Cutoff in {} sec   Best difficulty {}",
+                self.cutoff.map_or(0, |cutoff| cutoff
+                    .saturating_duration_since(Instant::now())
+                    .as_secs()),
+                self.best_difficulty
+            ),
+            String::new(),
+            format!(
+                "{} {:.0} H/sec",
+                "Hashrate".bold(),
+                self.thread_hashrates.iter().sum::<f64>()
+            ),
+        ];
+        for (i, chunk) in self.thread_hashrates.chunks(THREADS_PER_LINE).enumerate() {
+            let threads: Vec<String> = chunk
+                .iter()
+                .enumerate()
+                .map(|(j, hashrate)| {
+                    format!(
+                        "Thread {:>3} {:>8.0} H/sec",
+                        i * THREADS_PER_LINE + j,
+                        hashrate
+                    )
+                })
+                .collect();
+            lines.push(format!("  {}", threads.join("   ")));
+        }
+        lines.push(String::new());
+        lines.push("Busses".bold().to_string());
+        let busses: Vec<String> = self
+            .busses
+            .iter()
+            .enumerate()
+            .map(|(i, rewards)| format!("{}: {} ORE", i, amount_u64_to_string(*rewards)))
+            .collect();
+        lines.push(format!("  {}", busses.join("   ")));
+        lines.push(String::new());
+        lines.push("Recent rounds".bold().to_string());
+        lines.push(format!(
+            "  {:<19}  {:>10}  {:>6}  {:>12}  {}",
+            "Time", "Difficulty", "Status", "Reward (ORE)", "Signature"
+        ));
+        for round in self.rounds.iter().rev() {
+            let status = if round.landed {
+                format!("{:>6}", "landed").green()
+            } else {
+                format!("{:>6}", "failed").red()
+            };
+            lines.push(format!(
+                "  {:<19}  {:>10}  {}  {:>12}  {}",
+                local_time(round.timestamp).format("%Y-%m-%d %H:%M:%S"),
+                round.difficulty,
+                status,
+                round.reward_string(),
+                round
+                    .signature
+                    .as_deref()
+                    .or(round.error.as_deref())
+                    .unwrap_or("-")
+            ));
+        }
+        lines.push(String::new());
+        lines
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        restore_terminal();
+
+        // Print what was captured since the last render, now the screen is back
+        for line in CAPTURED.lock().unwrap().take().unwrap_or_default() {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Prints a line, or adds it to the dashboard's log while the dashboard is shown.
+///
+/// For output from outside the mining loop, which has no handle on the dashboard.
+pub fn print_line(message: String) {
+    match CAPTURED.lock().unwrap().as_mut() {
+        Some(lines) => lines.push(message),
+        None => println!("{}", message),
+    }
+}
+
+/// Leaves the dashboard, if shown, printing its captured lines, then prints the error and
+/// exits.
+pub fn exit_with_error(message: String) -> ! {
+    if let Some(lines) = CAPTURED.lock().unwrap().take() {
+        restore_terminal();
+        for line in lines {
+            println!("{}", line);
+        }
+    }
+    println!("{} {}", "ERROR".bold().red(), message);
+    std::process::exit(1);
+}
+
+/// Whether a dashboard is shown, so other output must go through its log.
+fn is_shown() -> bool {
+    CAPTURED.lock().unwrap().is_some()
+}
+
+/// A transaction progress spinner, or a hidden one while the dashboard is shown.
+pub fn new_progress_bar() -> ProgressBar {
+    if is_shown() {
+        ProgressBar::hidden()
+    } else {
+        spinner::new_progress_bar()
+    }
+}
+
+/// Finishes a spinner from `new_progress_bar`, adding the message to the dashboard's log
+/// while the dashboard is shown.
+pub fn finish_progress(progress_bar: &ProgressBar, message: String) {
+    if is_shown() {
+        print_line(message.clone());
+    }
+    progress_bar.finish_with_message(message);
+}
+
+/// Shows the cursor and leaves the alternate screen.
+pub fn restore_terminal() {
+    let term = Term::stdout();
+    term.show_cursor().ok();
+    term.write_str(LEAVE_ALTERNATE_SCREEN).ok();
+}
+
+impl Miner {
+    /// Fetches the wallet and bus balances shown on the dashboard.
+    pub async fn refresh_dashboard(&self, dashboard: &mut Dashboard, proof: Proof, config: Config) {
+        let signer = self.signer();
+        dashboard.stake = proof.balance;
+        dashboard.multiplier = stake_multiplier(proof.balance, config.top_balance);
+        if let Ok(balance) = self.rpc_client.get_balance(&signer.pubkey()).await {
+            dashboard.sol_balance = balance;
+        }
+        let token_account = get_associated_token_address(&signer.pubkey(), &MINT_ADDRESS);
+        if let Ok(Some(token_account)) = self.rpc_client.get_token_account(&token_account).await {
+            dashboard.ore_balance = Some(token_account.token_amount.ui_amount_string);
+        }
+        if let Ok(accounts) = self.rpc_client.get_multiple_accounts(&BUS_ADDRESSES).await {
+            dashboard.busses = accounts
+                .iter()
+                .map(|account| {
+                    account
+                        .as_ref()
+                        .and_then(|account| Bus::try_from_bytes(&account.data).ok())
+                        .map_or(0, |bus| bus.rewards)
+                })
+                .collect();
+        }
+        dashboard.render();
+    }
+}
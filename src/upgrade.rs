@@ -1,9 +1,10 @@
 use colored::*;
 use solana_sdk::{pubkey::Pubkey, signer::Signer};
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
 use spl_token::amount_to_ui_amount;
 
 use crate::{
-    cu_limits::CU_LIMIT_UPGRADE,
+    cu_limits::{CU_LIMIT_ATA, CU_LIMIT_UPGRADE},
     send_and_confirm::ComputeBudget,
     utils::{amount_f64_to_u64_v1, ask_confirm},
     Miner, UpgradeArgs,
@@ -12,6 +13,45 @@ use crate::{
 impl Miner {
     pub async fn upgrade(&self, args: UpgradeArgs) {
         let signer = &self.signer();
+
+        // Sign-only mode must not depend on chain state
+        if args.sign.sign_only {
+            let Some(amount) = args.amount else {
+                println!("--amount is required with --sign-only");
+                return;
+            };
+            let beneficiary = spl_associated_token_account::get_associated_token_address(
+                &signer.pubkey(),
+                &ore_api::consts::MINT_ADDRESS,
+            );
+            let sender = spl_associated_token_account::get_associated_token_address(
+                &signer.pubkey(),
+                &ore_api::consts::MINT_V1_ADDRESS,
+            );
+            let ixs = [
+                create_associated_token_account_idempotent(
+                    &signer.pubkey(),
+                    &signer.pubkey(),
+                    &ore_api::consts::MINT_ADDRESS,
+                    &spl_token::id(),
+                ),
+                ore_api::instruction::upgrade(
+                    signer.pubkey(),
+                    beneficiary,
+                    sender,
+                    amount_f64_to_u64_v1(amount),
+                ),
+            ];
+            self.send_or_sign(
+                &ixs,
+                ComputeBudget::Fixed(CU_LIMIT_UPGRADE + CU_LIMIT_ATA),
+                &args.sign,
+            )
+            .await
+            .ok();
+            return;
+        }
+
         let beneficiary = self.get_or_initialize_ata().await;
         let (sender, sender_balance) = self.get_ata_v1().await;
 
@@ -28,19 +68,22 @@ impl Miner {
         let amount = amount_f64_to_u64_v1(amount_f64);
         let amount_ui = amount_to_ui_amount(amount, ore_api::consts::TOKEN_DECIMALS_V1);
 
-        if !ask_confirm(
-            format!(
-                "\n You are about to upgrade {}. \n\nAre you sure you want to continue? [Y/n]",
-                format!("{} ORE", amount_ui).bold(),
+        if !self.yes
+            && !ask_confirm(
+                format!(
+                    "\n You are about to upgrade {}. \n\nAre you sure you want to continue?",
+                    format!("{} ORE", amount_ui).bold(),
+                )
+                .as_str(),
+                true,
             )
-            .as_str(),
-        ) {
+        {
             return;
         }
 
         let ix = ore_api::instruction::upgrade(signer.pubkey(), beneficiary, sender, amount);
         match self
-            .send_and_confirm(&[ix], ComputeBudget::Fixed(CU_LIMIT_UPGRADE), false)
+            .send_or_sign(&[ix], ComputeBudget::Fixed(CU_LIMIT_UPGRADE), &args.sign)
             .await
         {
             Ok(_sig) => {}
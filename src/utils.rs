@@ -1,4 +1,6 @@
-use std::io::Read;
+use std::{io::IsTerminal, path::PathBuf};
+
+use colored::*;
 
 use cached::proc_macro::cached;
 use ore_api::{
@@ -29,11 +31,26 @@ pub async fn get_config(client: &RpcClient) -> Config {
     *Config::try_from_bytes(&data).expect("Failed to parse config account")
 }
 
+/// Fetches the program config, or `None` if it can't be read.
+pub async fn try_get_config(client: &RpcClient) -> Option<Config> {
+    let data = client.get_account_data(&CONFIG_ADDRESS).await.ok()?;
+    Config::try_from_bytes(&data).ok().copied()
+}
+
 pub async fn get_proof_with_authority(client: &RpcClient, authority: Pubkey) -> Proof {
     let proof_address = proof_pubkey(authority);
     get_proof(client, proof_address).await
 }
 
+/// Fetches a miner's proof, or `None` if it has not opened one yet.
+pub async fn try_get_proof_with_authority(client: &RpcClient, authority: Pubkey) -> Option<Proof> {
+    let data = client
+        .get_account_data(&proof_pubkey(authority))
+        .await
+        .ok()?;
+    Proof::try_from_bytes(&data).ok().copied()
+}
+
 pub async fn get_proof(client: &RpcClient, address: Pubkey) -> Proof {
     let data = client
         .get_account_data(&address)
@@ -66,19 +83,54 @@ pub fn amount_f64_to_u64_v1(amount: f64) -> u64 {
     (amount * 10f64.powf(TOKEN_DECIMALS_V1 as f64)) as u64
 }
 
-pub fn ask_confirm(question: &str) -> bool {
-    println!("{}", question);
+/// Reward multiplier earned by a stake, relative to the largest stake on the network.
+///
+/// The program pays an extra `min(balance, top_balance) / top_balance` of the base reward,
+/// so the multiplier ranges from 1x with no stake to 2x at the top balance.
+pub fn stake_multiplier(balance: u64, top_balance: u64) -> f64 {
+    if top_balance == 0 {
+        return 1.0;
+    }
+    1.0 + balance.min(top_balance) as f64 / top_balance as f64
+}
+
+/// Asks a yes/no question on stdin, returning `default` on an empty answer.
+///
+/// Declines without reading if stdin is not a terminal, so unattended runs fail safely
+/// instead of waiting on input that never comes. Use `--yes` to skip the prompt.
+pub fn ask_confirm(question: &str, default: bool) -> bool {
+    println!("{} {}", question, if default { "[Y/n]" } else { "[y/N]" });
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        println!(
+            "{} Cannot ask for confirmation without a terminal. Pass --yes to continue.",
+            "ERROR".bold().red()
+        );
+        return false;
+    }
     loop {
-        let mut input = [0];
-        let _ = std::io::stdin().read(&mut input);
-        match input[0] as char {
-            'y' | 'Y' => return true,
-            'n' | 'N' => return false,
-            _ => println!("y/n only please."),
+        let mut input = String::new();
+        match stdin.read_line(&mut input) {
+            Ok(0) | Err(_) => return false,
+            Ok(_) => {}
+        }
+        match input.trim().to_lowercase().as_str() {
+            "" => return default,
+            "y" | "yes" => return true,
+            "n" | "no" => return false,
+            _ => println!("Please answer y or n."),
         }
     }
 }
 
+/// Directory for local ore-cli state (keystores, history, profile).
+pub fn ore_dir() -> PathBuf {
+    dirs_next::home_dir()
+        .expect("Failed to locate home directory")
+        .join(".config")
+        .join("ore-cli")
+}
+
 #[cached]
 pub fn proof_pubkey(authority: Pubkey) -> Pubkey {
     Pubkey::find_program_address(&[PROOF, authority.as_ref()], &ore_api::ID).0